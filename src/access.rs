@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::Value;
+use yansi::Paint;
+
+// the Common and Combined Log Format of apache and nginx, the ingress-nginx
+// controller adds the request length and the request time after the user agent
+const ACCESS_RE: &str = r#"^(?P<remote>\S+) \S+ \S+ \[(?P<ts>[^\]]+)\] "(?P<request>[^"]*)" (?P<status>\d{3}) (?P<bytes>\d+|-)(?: "[^"]*" "(?P<agent>[^"]*)"(?: \d+ (?P<duration>\d+(?:\.\d+)?))?)?"#;
+
+// requests slower than this are highlighted in yellow, and in red when they
+// are slower than the second one (in seconds)
+const SLOW_REQUEST: f64 = 1.0;
+const VERY_SLOW_REQUEST: f64 = 5.0;
+
+// a json log is only an access log with one of these nginx variables, the apps
+// logging a status and a method are not
+const NGINX_KEYS: [&str; 5] = [
+    "remote_addr",
+    "request_time",
+    "body_bytes_sent",
+    "bytes_sent",
+    "upstream_response_time",
+];
+
+#[derive(Debug, Default, PartialEq)]
+struct Request {
+    remote: String,
+    ts: String,
+    method: String,
+    path: String,
+    status: u16,
+    bytes: String,
+    duration: Option<f64>,
+    agent: String,
+}
+
+fn level_for_status(status: u16) -> &'static str {
+    match status {
+        500.. => "ERROR",
+        400..=499 => "WARNING",
+        _ => "INFO",
+    }
+}
+
+fn paint_status(status: u16) -> Paint<u16> {
+    match status {
+        500.. => Paint::red(status),
+        400..=499 => Paint::yellow(status),
+        300..=399 => Paint::cyan(status),
+        _ => Paint::green(status),
+    }
+}
+
+fn paint_duration(duration: f64) -> Paint<String> {
+    let text = format!("{duration}s");
+    if duration >= VERY_SLOW_REQUEST {
+        Paint::red(text)
+    } else if duration >= SLOW_REQUEST {
+        Paint::yellow(text)
+    } else {
+        Paint::new(text)
+    }
+}
+
+// the first key of a json log set to a string or a number
+fn field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| match value.get(key)? {
+        Value::String(s) if !s.is_empty() && s != "-" => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+fn parse_clf(line: &str) -> Option<Request> {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    let re = REG.get_or_init(|| Regex::new(ACCESS_RE).unwrap());
+    let capture = re.captures(line)?;
+    let mut request = capture["request"].split_whitespace();
+    Some(Request {
+        remote: capture["remote"].to_string(),
+        ts: capture["ts"].to_string(),
+        method: request.next().unwrap_or_default().to_string(),
+        path: request.next().unwrap_or_default().to_string(),
+        status: capture["status"].parse().ok()?,
+        bytes: capture["bytes"].to_string(),
+        duration: capture
+            .name("duration")
+            .and_then(|d| d.as_str().parse().ok()),
+        agent: capture
+            .name("agent")
+            .map(|a| a.as_str().to_string())
+            .unwrap_or_default(),
+    })
+}
+
+// the nginx `log_format json` with the names of the nginx variables, or the
+// names suggested by the ingress-nginx documentation
+fn parse_json(line: &str) -> Option<Request> {
+    let value = serde_json::from_str::<Value>(line).ok()?;
+    if !NGINX_KEYS.iter().any(|key| value.get(key).is_some()) {
+        return None;
+    }
+    let status = field(&value, &["status"])?.parse().ok()?;
+    let request = field(&value, &["request"]).unwrap_or_default();
+    let mut request = request.split_whitespace();
+    let method = field(&value, &["request_method", "method"])
+        .or_else(|| request.next().map(ToString::to_string))?;
+    let path = field(&value, &["request_uri", "uri", "path"])
+        .or_else(|| request.next().map(ToString::to_string))
+        .unwrap_or_default();
+    Some(Request {
+        remote: field(&value, &["remote_addr"]).unwrap_or_default(),
+        ts: field(&value, &["time_iso8601", "time_local", "time"]).unwrap_or_default(),
+        method,
+        path,
+        status,
+        bytes: field(&value, &["body_bytes_sent", "bytes_sent"]).unwrap_or_default(),
+        duration: field(&value, &["request_time", "duration"]).and_then(|d| d.parse().ok()),
+        agent: field(&value, &["http_user_agent"]).unwrap_or_default(),
+    })
+}
+
+/// Parse the access logs of apache and nginx, the level is derived from the
+/// status code of the response.
+pub fn parse_access(line: &str, time_format: &str) -> Option<HashMap<String, String>> {
+    let request = parse_clf(line).or_else(|| parse_json(line))?;
+
+    let mut text = format!(
+        "{} {} {}",
+        request.method,
+        request.path,
+        paint_status(request.status)
+    );
+    if !request.remote.is_empty() {
+        text = format!("{} {text}", Paint::new(&request.remote).dimmed());
+    }
+    if !request.bytes.is_empty() && request.bytes != "-" {
+        text = format!("{text} {}B", request.bytes);
+    }
+    if let Some(duration) = request.duration {
+        text = format!("{text} {}", paint_duration(duration));
+    }
+    if !request.agent.is_empty() && request.agent != "-" {
+        text = format!(
+            "{text} {}",
+            Paint::new(format!("\"{}\"", request.agent)).dimmed()
+        );
+    }
+
+    let mut msg = HashMap::new();
+    msg.insert("msg".to_string(), text);
+    msg.insert(
+        "level".to_string(),
+        level_for_status(request.status).to_string(),
+    );
+    if !request.ts.is_empty() {
+        msg.insert(
+            "ts".to_string(),
+            crate::utils::convert_clf_ts(&request.ts, time_format),
+        );
+    }
+    Some(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clf() {
+        let request = parse_clf(
+            r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#,
+        )
+        .unwrap();
+        assert_eq!(request.remote, "127.0.0.1");
+        assert_eq!(request.ts, "10/Oct/2000:13:55:36 -0700");
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/apache_pb.gif");
+        assert_eq!(request.status, 200);
+        assert_eq!(request.bytes, "2326");
+        assert_eq!(request.duration, None);
+        assert_eq!(request.agent, "");
+    }
+
+    #[test]
+    fn test_parse_ingress_nginx() {
+        let request = parse_clf(
+            r#"10.0.0.1 - - [02/Jan/2024:03:04:05 +0000] "POST /api HTTP/1.1" 503 0 "-" "curl/8.0" 120 6.002 [default-api-80] [] 10.1.0.4:8080 0 6.001 503 4f8c"#,
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.status, 503);
+        assert_eq!(request.duration, Some(6.002));
+        assert_eq!(request.agent, "curl/8.0");
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let request = parse_json(
+            r#"{"time":"2024-01-02T03:04:05+00:00","remote_addr":"10.0.0.1","request":"GET /x?y=1 HTTP/1.1","status":"404","body_bytes_sent":"12","request_time":"0.002","http_user_agent":"curl/8.0"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/x?y=1");
+        assert_eq!(request.status, 404);
+        assert_eq!(request.bytes, "12");
+        assert_eq!(request.duration, Some(0.002));
+
+        let request = parse_json(
+            r#"{"method":"GET","path":"/","status":200,"duration":1.5,"remote_addr":"10.0.0.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.status, 200);
+        assert_eq!(request.duration, Some(1.5));
+        assert!(parse_json(r#"{"level":"info","msg":"hello"}"#).is_none());
+        assert!(parse_json(
+            r#"{"level":"info","msg":"request handled","status":200,"method":"GET","path":"/healthz"}"#
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_parse_access() {
+        let msg = parse_access(
+            r#"10.0.0.1 - - [02/Jan/2024:03:04:05 +0100] "GET / HTTP/1.1" 500 5 "-" "-" 80 1.2"#,
+            "%H:%M:%S",
+        )
+        .unwrap();
+        assert_eq!(msg["level"], "ERROR");
+        assert_eq!(msg["ts"], "02:04:05");
+        assert_eq!(
+            msg["msg"],
+            format!(
+                "{} GET / {} 5B {}",
+                Paint::new("10.0.0.1").dimmed(),
+                Paint::red(500),
+                Paint::yellow("1.2s")
+            )
+        );
+        assert_eq!(level_for_status(404), "WARNING");
+        assert_eq!(level_for_status(302), "INFO");
+        assert!(parse_access("hello moto", "%H:%M:%S").is_none());
+    }
+}
@@ -0,0 +1,594 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use yansi::Paint;
+
+use crate::config::{self, Config, LogLevel};
+use crate::webhook::Webhook;
+
+/// A rule running a command or posting the record to an url when a record
+/// matches all its conditions, from --action or a --action-file
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(default)]
+    pub name: String,
+    /// a regexp matching the line, its named groups can be used in the command
+    pub regexp: Option<String>,
+    /// the level of the record, e.g: error
+    pub level: Option<String>,
+    /// a field of the json record, `key` when it's present or `key=regexp`
+    pub field: Option<String>,
+    #[serde(default)]
+    pub command: String,
+    /// post the record as json to this url instead of running a command
+    pub url: Option<String>,
+    /// post the records in json arrays of this size, 1 by default
+    pub batch: Option<usize>,
+    /// the timeout of a post, 5s by default
+    pub timeout: Option<String>,
+    /// how many times a failed post is retried, 3 by default
+    pub retries: Option<u32>,
+    /// don't run the command again before this, e.g: 30s
+    pub debounce: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Action {
+    pub name: String,
+    regexp: Option<Regex>,
+    level: Option<&'static LogLevel>,
+    field: Option<(String, Option<Regex>)>,
+    command: String,
+    webhook: Option<Webhook>,
+    debounce: Option<Duration>,
+    last_run: Mutex<Option<Instant>>,
+}
+
+impl Action {
+    pub fn new(rule: Rule) -> Result<Action, String> {
+        let name = if !rule.name.is_empty() {
+            rule.name
+        } else if let Some(url) = &rule.url {
+            url.clone()
+        } else {
+            rule.command.clone()
+        };
+        match (rule.command.trim().is_empty(), &rule.url) {
+            (true, None) => return Err(format!("action {name} has no command or url")),
+            (false, Some(_)) => {
+                return Err(format!(
+                    "action {name} can't have both a command and an url"
+                ))
+            }
+            _ => {}
+        }
+        if rule.regexp.is_none() && rule.level.is_none() && rule.field.is_none() {
+            return Err(format!(
+                "action {name} needs a regexp, a level or a field to match"
+            ));
+        }
+        let compile = |re: &str| {
+            Regex::new(re).map_err(|e| format!("invalid regexp {re} in action {name}: {e}"))
+        };
+        let regexp = rule.regexp.as_deref().map(compile).transpose()?;
+        let duration = |d: Option<&str>| {
+            d.map(crate::utils::parse_duration)
+                .transpose()
+                .map(|d| d.map(|d| d.to_std().unwrap_or_default()))
+        };
+        let debounce = duration(rule.debounce.as_deref())?;
+        let timeout = duration(rule.timeout.as_deref())?.unwrap_or(Duration::from_secs(5));
+        let webhook = match rule.url {
+            // the records go to a local relay, we are built without tls
+            Some(url) if !url.starts_with("http://") => {
+                return Err(format!(
+                    "invalid url {url} in action {name}, only http:// is supported"
+                ))
+            }
+            Some(url) => Some(Webhook::new(
+                url,
+                rule.batch.unwrap_or(1),
+                timeout,
+                rule.retries.unwrap_or(3),
+            )),
+            None => None,
+        };
+        let field = match rule.field.as_deref().map(|f| f.split_once('=')) {
+            None => None,
+            Some(Some((key, re))) => Some((key.to_string(), Some(compile(re)?))),
+            Some(None) => rule.field.map(|key| (key, None)),
+        };
+        Ok(Action {
+            regexp,
+            level: rule
+                .level
+                .map(|level| config::level_from_str(&level.to_lowercase())),
+            field,
+            command: rule.command,
+            webhook,
+            debounce,
+            last_run: Mutex::default(),
+            name,
+        })
+    }
+
+    // if the command already ran in the debounce time, or record that it runs now
+    fn debounced(&self) -> bool {
+        let mut last_run = self.last_run.lock().unwrap();
+        if let (Some(debounce), Some(last)) = (self.debounce, *last_run) {
+            if last.elapsed() < debounce {
+                return true;
+            }
+        }
+        *last_run = Some(Instant::now());
+        false
+    }
+
+    // the values the command can use when the record match
+    fn matches(
+        &self,
+        line: &str,
+        fields: &HashMap<String, String>,
+        json: Option<&Value>,
+    ) -> Option<HashMap<String, String>> {
+        let mut values = fields.clone();
+        values.insert("line".to_string(), line.to_string());
+        values.insert(String::new(), line.to_string());
+        if let Some(regexp) = &self.regexp {
+            let captures = regexp.captures(line)?;
+            values.insert(String::new(), captures[0].to_string());
+            for name in regexp.capture_names().flatten() {
+                if let Some(value) = captures.name(name) {
+                    values.insert(name.to_string(), value.as_str().to_string());
+                }
+            }
+        }
+        if let Some(level) = self.level {
+            let record = fields.get("level")?;
+            if config::level_from_str(&record.to_lowercase()) != level {
+                return None;
+            }
+        }
+        if let Some((key, regexp)) = &self.field {
+            let value = crate::ecs::lookup(json?, key).map(value_to_string)?;
+            if regexp.as_ref().is_some_and(|re| !re.is_match(&value)) {
+                return None;
+            }
+        }
+        Some(values)
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Build the actions of the --action (a rule as json) and of the --action-file
+/// (a json array of rules).
+pub fn parse_rules(actions: &[String], file: Option<&str>) -> Result<Vec<Action>, String> {
+    let mut rules = actions
+        .iter()
+        .map(|action| {
+            serde_json::from_str::<Rule>(action)
+                .map_err(|e| format!("invalid action {action}: {e}"))
+        })
+        .collect::<Result<Vec<Rule>, String>>()?;
+    if let Some(file) = file {
+        let content = std::fs::read_to_string(file).map_err(|e| format!("file {file}, {e}"))?;
+        rules.extend(
+            serde_json::from_str::<Vec<Rule>>(&content)
+                .map_err(|e| format!("invalid actions in {file}: {e}"))?,
+        );
+    }
+    rules.into_iter().map(Action::new).collect()
+}
+
+// the environment variable of a value, e.g: SNAZY_MATCH for {} or
+// SNAZY_FIELD_ERROR_CODE for {field.error.code}
+fn env_name(name: &str) -> String {
+    if name.is_empty() {
+        return String::from("SNAZY_MATCH");
+    }
+    let name = name
+        .to_uppercase()
+        .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    format!("SNAZY_{name}")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+// the quoting of the shell at the end of the text
+fn quoting(text: &str, mut quote: Quote) -> Quote {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Quote::None | Quote::Double, '\\') => {
+                chars.next();
+            }
+            (Quote::None, '\'') => quote = Quote::Single,
+            (Quote::None, '"') => quote = Quote::Double,
+            (Quote::Single, '\'') | (Quote::Double, '"') => quote = Quote::None,
+            _ => {}
+        }
+    }
+    quote
+}
+
+/// Replace the `{}` (the match), `{name}` (a named group, a parsed field like
+/// level, msg, ts, namespace, pod or container) and `{field.key}` (a field of
+/// the json record) of the command by environment variables, quoted for where
+/// they are in the command so the values are never interpreted by the shell.
+fn render(
+    command: &str,
+    values: &HashMap<String, String>,
+    json: Option<&Value>,
+) -> (String, HashMap<String, String>) {
+    let mut env: HashMap<String, String> = values
+        .iter()
+        .map(|(name, value)| (env_name(name), value.clone()))
+        .collect();
+    let re = Regex::new(r"\{([\w.@-]*)\}").unwrap();
+    let mut rendered = String::new();
+    let mut quote = Quote::None;
+    let mut last = 0;
+    for caps in re.captures_iter(command) {
+        let placeholder = caps.get(0).unwrap();
+        let before = &command[last..placeholder.start()];
+        quote = quoting(before, quote);
+        rendered.push_str(before);
+        last = placeholder.end();
+        let name = &caps[1];
+        let var = env_name(name);
+        if let Some(key) = name.strip_prefix("field.") {
+            let value = json
+                .and_then(|json| crate::ecs::lookup(json, key))
+                .map(value_to_string)
+                .unwrap_or_default();
+            env.insert(var.clone(), value);
+        }
+        env.entry(var.clone()).or_default();
+        // in single quotes we close them to expand the variable
+        let _ = match quote {
+            Quote::None => write!(rendered, "\"${{{var}}}\""),
+            Quote::Single => write!(rendered, "'\"${{{var}}}\"'"),
+            Quote::Double => write!(rendered, "${{{var}}}"),
+        };
+    }
+    rendered.push_str(&command[last..]);
+    (rendered, env)
+}
+
+// tell once how many times the actions were skipped, not on every record
+fn report_skipped(config: &Config, skipped: &mut HashMap<String, usize>) {
+    let mut skipped: Vec<_> = skipped.drain().collect();
+    skipped.sort();
+    for (name, count) in skipped {
+        eprintln!(
+            "Skipped action: {} {count} times, {} actions were still running",
+            Paint::cyan(&name),
+            config.action_max_running
+        );
+    }
+}
+
+// run the command with the record on its stdin, a thread waits for it to not
+// leave any zombie behind
+fn spawn(config: &Config, action: &Action, values: &HashMap<String, String>, json: Option<&Value>) {
+    let mut state = config.state.lock().unwrap();
+    state.actions.retain(|running| !running.is_finished());
+    if state.actions.len() >= config.action_max_running {
+        *state
+            .skipped_actions
+            .entry(action.name.clone())
+            .or_default() += 1;
+        return;
+    }
+    report_skipped(config, &mut state.skipped_actions);
+    let (command, mut env) = render(&action.command, values, json);
+    env.insert(String::from("SNAZY_ACTION"), action.name.clone());
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env)
+        .stdin(Stdio::piped())
+        // the output of the command would be mixed with the logs
+        .stdout(io::stderr())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("Cannot run action: {}: {e}", Paint::cyan(&action.name));
+            return;
+        }
+    };
+    eprintln!("Spawned command: for action: {}", Paint::cyan(&action.name));
+    let name = action.name.clone();
+    let record = values.get("line").cloned().unwrap_or_default();
+    state.actions.push(thread::spawn(move || {
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = writeln!(stdin, "{record}");
+        }
+        match child.wait() {
+            Ok(status) if !status.success() => {
+                eprintln!("Action {} failed: {status}", Paint::cyan(&name));
+            }
+            Err(e) => eprintln!("Action {} failed: {e}", Paint::cyan(&name)),
+            Ok(_) => {}
+        }
+    }));
+}
+
+// the record posted to the url of an action
+fn record(action: &Action, values: &HashMap<String, String>, json: Option<&Value>) -> Value {
+    let mut record: serde_json::Map<String, Value> = values
+        .iter()
+        .map(|(name, value)| {
+            let name = if name.is_empty() { "match" } else { name };
+            (name.to_string(), Value::String(value.clone()))
+        })
+        .collect();
+    record.insert("action".to_string(), Value::String(action.name.clone()));
+    if let Some(json) = json {
+        record.insert("record".to_string(), json.clone());
+    }
+    Value::Object(record)
+}
+
+/// Wait for the commands still running and post the records still queued,
+/// e.g: when we are done reading the logs
+pub fn wait(config: &Config) {
+    for action in &config.actions {
+        if let Some(webhook) = &action.webhook {
+            webhook.flush();
+        }
+    }
+    let running = std::mem::take(&mut config.state.lock().unwrap().actions);
+    for action in running {
+        let _ = action.join();
+    }
+    report_skipped(config, &mut config.state.lock().unwrap().skipped_actions);
+}
+
+/// Run the commands of the actions matching a record, the fields are the ones
+/// parsed from the line.
+pub fn trigger(config: &Config, actions: &[Action], line: &str, fields: &HashMap<String, String>) {
+    if actions.is_empty() {
+        return;
+    }
+    let mut fields = fields.clone();
+    let mut json_line = line;
+    if let Some(captures) = crate::parse::kail_regex().captures(line) {
+        for name in ["namespace", "pod", "container"] {
+            fields.insert(name.to_string(), captures[name].to_string());
+        }
+        json_line = captures.name("line").unwrap().as_str();
+    }
+    let json = serde_json::from_str::<Value>(json_line).ok();
+    for action in actions {
+        let Some(values) = action.matches(line, &fields, json.as_ref()) else {
+            continue;
+        };
+        if action.debounced() {
+            continue;
+        }
+        match &action.webhook {
+            Some(webhook) => webhook.send(&action.name, record(action, &values, json.as_ref())),
+            None => spawn(config, action, &values, json.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(rule: &str) -> Action {
+        Action::new(serde_json::from_str(rule).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(parse_rules(
+            &[String::from(r#"{"regexp":"foo","command":"true"}"#)],
+            None
+        )
+        .is_ok());
+        assert!(parse_rules(&[String::from(r#"{"command":"true"}"#)], None).is_err());
+        assert!(parse_rules(&[String::from(r#"{"regexp":"(","command":"true"}"#)], None).is_err());
+        assert!(parse_rules(&[String::from(r#"{"regexp":"foo","cmd":"true"}"#)], None).is_err());
+        assert!(parse_rules(&[], Some("/does/not/exist")).is_err());
+        assert!(parse_rules(
+            &[String::from(
+                r#"{"regexp":"foo","url":"http://localhost:8080"}"#
+            )],
+            None
+        )
+        .is_ok());
+        assert!(parse_rules(
+            &[String::from(
+                r#"{"regexp":"foo","url":"http://localhost:8080","command":"true"}"#
+            )],
+            None
+        )
+        .is_err());
+        assert!(parse_rules(
+            &[String::from(r#"{"regexp":"foo","url":"localhost:8080"}"#)],
+            None
+        )
+        .is_err());
+        assert!(parse_rules(
+            &[String::from(
+                r#"{"regexp":"foo","url":"https://hooks.example.com"}"#
+            )],
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let line = r#"{"level":"error","msg":"reconcile failed","error":{"code":"timeout"}}"#;
+        let mut fields = HashMap::new();
+        fields.insert("level".to_string(), "ERROR".to_string());
+        let json: Value = serde_json::from_str(line).unwrap();
+
+        let level = action(r#"{"level":"error","command":"true"}"#);
+        assert!(level.matches(line, &fields, Some(&json)).is_some());
+        let level = action(r#"{"level":"warn","command":"true"}"#);
+        assert!(level.matches(line, &fields, Some(&json)).is_none());
+
+        let field = action(r#"{"field":"error.code=time","command":"true"}"#);
+        assert!(field.matches(line, &fields, Some(&json)).is_some());
+        let field = action(r#"{"field":"error.reason","command":"true"}"#);
+        assert!(field.matches(line, &fields, Some(&json)).is_none());
+
+        let both =
+            action(r#"{"regexp":"reconcile (?P<what>\\w+)","level":"error","command":"true"}"#);
+        let values = both.matches(line, &fields, Some(&json)).unwrap();
+        assert_eq!(values[""], "reconcile failed");
+        assert_eq!(values["what"], "failed");
+    }
+
+    #[test]
+    fn test_render() {
+        let json: Value = serde_json::from_str(r#"{"error":{"code":"timeout"}}"#).unwrap();
+        let mut values = HashMap::new();
+        values.insert(String::new(), "the match".to_string());
+        values.insert("pod".to_string(), "web-1".to_string());
+        let (command, env) = render(
+            "{pod}: {} {field.error.code} {missing}.",
+            &values,
+            Some(&json),
+        );
+        assert_eq!(
+            command,
+            r#""${SNAZY_POD}": "${SNAZY_MATCH}" "${SNAZY_FIELD_ERROR_CODE}" "${SNAZY_MISSING}"."#
+        );
+        assert_eq!(env["SNAZY_POD"], "web-1");
+        assert_eq!(env["SNAZY_MATCH"], "the match");
+        assert_eq!(env["SNAZY_FIELD_ERROR_CODE"], "timeout");
+        assert_eq!(env["SNAZY_MISSING"], "");
+    }
+
+    #[test]
+    fn test_record() {
+        let hook = action(r#"{"name":"hook","level":"error","url":"http://localhost"}"#);
+        let json: Value = serde_json::from_str(r#"{"error":"timeout"}"#).unwrap();
+        let mut values = HashMap::new();
+        values.insert(String::new(), "the match".to_string());
+        values.insert("level".to_string(), "ERROR".to_string());
+        assert_eq!(
+            record(&hook, &values, Some(&json)),
+            serde_json::json!({
+                "action": "hook",
+                "match": "the match",
+                "level": "ERROR",
+                "record": {"error": "timeout"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_quoted() {
+        let values = HashMap::from([(String::new(), "the match".to_string())]);
+        let (command, _) = render(r#"echo '{} it' "{}" \'{}"#, &values, None);
+        assert_eq!(
+            command,
+            r#"echo ''"${SNAZY_MATCH}"' it' "${SNAZY_MATCH}" \'"${SNAZY_MATCH}""#
+        );
+    }
+
+    #[test]
+    fn test_debounce() {
+        let debounced = action(r#"{"regexp":"foo","command":"true","debounce":"1h"}"#);
+        assert!(!debounced.debounced());
+        assert!(debounced.debounced());
+        let always = action(r#"{"regexp":"foo","command":"true"}"#);
+        assert!(!always.debounced());
+        assert!(!always.debounced());
+        assert!(parse_rules(
+            &[String::from(
+                r#"{"regexp":"foo","command":"true","debounce":"soon"}"#
+            )],
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_spawn() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let rule = format!(
+            r#"{{"regexp":"said (?P<what>.*)","command":"cat > {0}; echo {{what}} >> {0}"}}"#,
+            out.display()
+        );
+        let config = Config {
+            actions: parse_rules(&[rule], None).unwrap(),
+            ..Config::default()
+        };
+        let line = "he said $(touch pwned)";
+        trigger(&config, &config.actions, line, &HashMap::new());
+        wait(&config);
+        let contents = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(contents, format!("{line}\n$(touch pwned)\n"));
+        assert!(!std::path::Path::new("pwned").exists());
+    }
+
+    #[test]
+    fn test_spawn_single_quoted() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let rule = format!(
+            r#"{{"regexp":"said (?P<what>.*)","command":"echo 'he said: {{what}} *' > {0}"}}"#,
+            out.display()
+        );
+        let config = Config {
+            actions: parse_rules(&[rule], None).unwrap(),
+            ..Config::default()
+        };
+        trigger(&config, &config.actions, "he said it's *", &HashMap::new());
+        wait(&config);
+        let contents = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(contents, "he said: it's * *\n");
+    }
+
+    #[test]
+    fn test_spawn_skipped() {
+        let config = Config {
+            actions: parse_rules(
+                &[r#"{"name":"slow","regexp":"hello","command":"sleep 1"}"#.to_string()],
+                None,
+            )
+            .unwrap(),
+            action_max_running: 1,
+            ..Config::default()
+        };
+        for _ in 0..5 {
+            trigger(&config, &config.actions, "hello", &HashMap::new());
+        }
+        assert_eq!(
+            config.state.lock().unwrap().skipped_actions,
+            HashMap::from([(String::from("slow"), 4)])
+        );
+        wait(&config);
+        assert!(config.state.lock().unwrap().skipped_actions.is_empty());
+    }
+}
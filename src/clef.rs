@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::Deserialize;
+use serde_json::Value;
+use yansi::Paint;
+
+// Serilog Compact Log Event Format
+// https://github.com/serilog/serilog-formatting-compact
+#[derive(Deserialize, Debug)]
+struct Clef {
+    #[serde(rename = "@t")]
+    timestamp: String,
+    #[serde(rename = "@mt")]
+    template: Option<String>,
+    #[serde(rename = "@m")]
+    message: Option<String>,
+    #[serde(rename = "@l")]
+    level: Option<String>,
+    #[serde(rename = "@x")]
+    exception: Option<String>,
+    #[serde(flatten)]
+    properties: BTreeMap<String, Value>,
+}
+
+/// map the serilog levels to ours, a missing level means Information
+pub fn clef_level(level: Option<&str>) -> &'static str {
+    match level {
+        Some("Verbose" | "Debug") => "DEBUG",
+        Some("Warning") => "WARNING",
+        Some("Error") => "ERROR",
+        Some("Fatal") => "FATAL",
+        _ => "INFO",
+    }
+}
+
+// format a property value, a `0.00` like format set the number of decimals
+fn format_value(value: &Value, format: Option<&str>) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => match format.and_then(|f| f.strip_prefix("0.")) {
+            Some(decimals) if decimals.chars().all(|c| c == '0') => {
+                format!("{:.*}", decimals.len(), n.as_f64().unwrap_or_default())
+            }
+            _ => n.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+/// render a message template like `User {UserId} logged in` with the
+/// properties of the event, the values are highlighted.
+pub fn render_template(template: &str, properties: &BTreeMap<String, Value>) -> String {
+    let mut ret = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                ret.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                ret.push('}');
+            }
+            '{' => {
+                let mut token = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    token.push(c);
+                }
+                if !closed {
+                    ret.push('{');
+                    ret.push_str(&token);
+                    break;
+                }
+                // {@Name,10:format} where @ and $ are destructuring hints
+                let (name, format) = match token.split_once(':') {
+                    Some((name, format)) => (name, Some(format)),
+                    None => (token.as_str(), None),
+                };
+                let name = name.split(',').next().unwrap_or_default();
+                let name = name.trim_start_matches(['@', '$']);
+                match properties.get(name) {
+                    Some(value) => {
+                        ret.push_str(&Paint::yellow(format_value(value, format)).to_string());
+                    }
+                    None => {
+                        let _ = write!(ret, "{{{token}}}");
+                    }
+                }
+            }
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+pub fn parse_clef(line: &str, time_format: &str) -> Option<HashMap<String, String>> {
+    let clef = serde_json::from_str::<Clef>(line).ok()?;
+    let mut msg = HashMap::new();
+    let mut text = match (&clef.template, &clef.message) {
+        (Some(template), _) => render_template(template, &clef.properties),
+        (None, Some(message)) => message.clone(),
+        (None, None) => String::new(),
+    };
+    if let Some(exception) = &clef.exception {
+        text.push_str(&crate::utils::indented_block(exception));
+    }
+    msg.insert("msg".to_string(), text.trim().to_string());
+    msg.insert(
+        "level".to_string(),
+        clef_level(clef.level.as_deref()).to_string(),
+    );
+    msg.insert(
+        "ts".to_string(),
+        crate::utils::convert_str_to_ts(&clef.timestamp, time_format),
+    );
+    Some(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let mut properties = BTreeMap::new();
+        properties.insert(String::from("UserId"), Value::from("bob"));
+        properties.insert(String::from("Elapsed"), Value::from(34.123));
+        properties.insert(String::from("Count"), Value::from(3));
+        assert_eq!(
+            render_template(
+                "User {UserId} logged in after {Elapsed:0.00} ms, {@Count,5} tries {{literal}} {Missing}",
+                &properties
+            ),
+            format!(
+                "User {} logged in after {} ms, {} tries {{literal}} {{Missing}}",
+                Paint::yellow("bob"),
+                Paint::yellow("34.12"),
+                Paint::yellow("3")
+            )
+        );
+        assert_eq!(
+            render_template("unclosed {UserId", &properties),
+            "unclosed {UserId"
+        );
+    }
+
+    #[test]
+    fn test_parse_clef() {
+        let line =
+            r#"{"@t":"2016-06-07T03:44:57.8532799Z","@mt":"Hello, {User}","User":"nblumhardt"}"#;
+        let msg = parse_clef(line, "%H:%M:%S").unwrap();
+        assert_eq!(
+            msg["msg"],
+            format!("Hello, {}", Paint::yellow("nblumhardt"))
+        );
+        assert_eq!(msg["level"], "INFO");
+        assert_eq!(msg["ts"], "03:44:57");
+
+        let line = r#"{"@t":"2016-06-07T03:44:57.8532799Z","@m":"Oops","@l":"Error","@x":"System.Exception: boom\n   at Program.Main()"}"#;
+        let msg = parse_clef(line, "%H:%M:%S").unwrap();
+        assert!(msg["msg"].starts_with("Oops\n    "));
+        assert!(msg["msg"].contains("System.Exception: boom"));
+        assert!(msg["msg"].contains("\n    "));
+        assert_eq!(msg["msg"].lines().count(), 3);
+        assert_eq!(msg["level"], "ERROR");
+
+        assert!(parse_clef(r#"{"level":"info","msg":"hello"}"#, "%H:%M:%S").is_none());
+    }
+}
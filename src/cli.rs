@@ -1,8 +1,9 @@
-use crate::config::{ColorWhen, Config, LogLevel};
+use crate::config::{ColorWhen, Config, LogLevel, TimeMode};
 use clap::{Command, CommandFactory, Parser, ValueHint};
 use clap_complete::{generate, Generator, Shell};
 use is_terminal::IsTerminal;
 use std::collections::HashMap;
+use std::sync::Mutex;
 use std::{env, io};
 use yansi::{Color, Paint};
 
@@ -49,6 +50,20 @@ struct Args {
     /// Filter the json logs by log level. You can have multiple log levels.
     pub filter_levels: Vec<LogLevel>,
 
+    #[arg(long, verbatim_doc_comment)]
+    /// Filter the `tkn` logs by task
+    ///
+    /// Only show the lines of the `tkn pr logs` output coming from this task.
+    /// You can have multiple tasks.
+    pub filter_tasks: Vec<String>,
+
+    #[arg(long, verbatim_doc_comment)]
+    /// Filter the `tkn` logs by step
+    ///
+    /// Only show the lines of the `tkn pr logs` output coming from this step.
+    /// You can have multiple steps.
+    pub filter_steps: Vec<String>,
+
     #[clap(
         long,
         short = 'c',
@@ -65,12 +80,67 @@ struct Args {
     /// 'always':    always use colorized output
     pub color: ColorWhen,
 
-    #[arg(long, default_value = "%H:%M:%S", env = "SNAZY_TIME_FORMAT")]
-    /// Filter by log level
+    #[arg(
+        long,
+        default_value = "%H:%M:%S",
+        env = "SNAZY_TIME_FORMAT",
+        verbatim_doc_comment
+    )]
+    /// The format of the timestamps
     ///
-    /// A timeformat as documented by the strftime(3) manpage.
+    /// A timeformat as documented by the strftime(3) manpage or a preset:
+    /// 'short':     %H:%M:%S
+    /// 'ms':        %H:%M:%S%.3f
+    /// 'iso':       %Y-%m-%dT%H:%M:%S%.3f%:z
+    /// 'full':      %Y-%m-%d %H:%M:%S%.9f %Z
     pub time_format: String,
 
+    #[arg(
+        long,
+        verbatim_doc_comment,
+        default_value = "UTC",
+        env = "SNAZY_TIMEZONE"
+    )]
+    /// The timezone of the timestamps shown
+    ///
+    /// `local` for the timezone of the system or a name of the tz database,
+    /// e.g: Europe/Paris. The timezone can be shown with %Z or %z in
+    /// --time-format.
+    pub timezone: String,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = TimeMode::Absolute,
+        value_name = "mode",
+        hide_possible_values = true,
+        env = "SNAZY_TIME_MODE",
+        verbatim_doc_comment
+    )]
+    /// How to show the timestamps
+    ///
+    /// 'absolute':  the time of the record with --time-format (default)
+    /// 'relative':  the time since the first record, e.g: +00:01.234
+    /// 'delta':     the time since the previous record
+    pub time_mode: TimeMode,
+
+    #[arg(long, value_parser = crate::utils::parse_duration, verbatim_doc_comment)]
+    /// Show a separator when the records are more than this apart
+    ///
+    /// e.g: 30s, 5m, 1h or 500ms
+    pub gap: Option<chrono::Duration>,
+
+    /// Show the time snazy read the records without a timestamp
+    #[arg(long, action(clap::ArgAction::SetTrue), env = "SNAZY_ADD_TIMESTAMP")]
+    pub add_timestamp: bool,
+
+    /// Show a summary of the logs on stderr at the end or on ctrl-c
+    ///
+    /// The records by level and by kail namespace/pod, the skipped and the raw
+    /// lines, the time span and the most frequent errors
+    #[arg(long, action(clap::ArgAction::SetTrue), verbatim_doc_comment)]
+    pub summary: bool,
+
     #[arg(
         long,
         verbatim_doc_comment,
@@ -83,10 +153,51 @@ struct Args {
     /// values."
     pub kail_prefix_format: String,
 
+    #[arg(
+        long,
+        verbatim_doc_comment,
+        default_value = "{service}",
+        env = "SNAZY_COMPOSE_PREFIX_FORMAT"
+    )]
+    /// Set the format on how to print the docker compose prefix.
+    ///
+    /// The {service} tag will be replaced by the name of the service.
+    pub compose_prefix_format: String,
+
+    #[arg(
+        long,
+        verbatim_doc_comment,
+        default_value = "[{task}:{step}]",
+        env = "SNAZY_TKN_PREFIX_FORMAT"
+    )]
+    /// Set the format on how to print the `tkn` prefix.
+    ///
+    /// The {task} and {step} tags will be replaced by their values.
+    pub tkn_prefix_format: String,
+
+    #[arg(
+        long,
+        verbatim_doc_comment,
+        default_value = "{service.name}",
+        env = "SNAZY_OTEL_PREFIX_FORMAT"
+    )]
+    /// Set the format on how to print the OpenTelemetry prefix.
+    ///
+    /// Any resource attribute can be used as a tag, e.g: {service.name} or
+    /// {k8s.pod.name} will be replaced by their values.
+    pub otel_prefix_format: String,
+
     #[arg(long, action(clap::ArgAction::SetTrue))]
     /// Hide container prefix when showing the log with kail
     pub kail_no_prefix: bool,
 
+    #[arg(long, action(clap::ArgAction::SetTrue))]
+    /// Hide the content of the GitHub Actions ##[group] sections
+    ///
+    /// Only the title of the groups are shown, the errors and warnings
+    /// annotations inside the groups are still shown.
+    pub collapse_groups: bool,
+
     /// Pretty emojis instead of boring text level
     #[arg(long, action(clap::ArgAction::SetTrue), env = "SNAZY_LEVEL_SYMBOLS")]
     pub level_symbols: bool,
@@ -94,17 +205,31 @@ struct Args {
     #[arg(short = 'k', long, verbatim_doc_comment)]
     /// Keys / Values for JSON Parsing
     ///
-    /// The keys needed to be passed are: msg (message), level (logging level),
-    /// ts (timestamp).
+    /// The keys are: msg (message), level (logging level), ts (timestamp), the
+    /// ones not given are guessed as usual. Any other key (e.g: caller, logger,
+    /// `trace_id`) can be mapped and used in --json-msg-format.
     ///
     /// For example:
     ///
-    /// `snazy -k msg=message -k level=level -k ts=ts`
+    /// `snazy -k msg=/message -k level=/severity -k caller=/src/line`
     ///
-    /// will parse the JSON log file and use the (`message`, `level`, `ts`) keys
-    /// from the json as (`msg`), (`level`) and (`ts`) for snazy.
+    /// will parse the JSON log file and use the (`message`, `severity`) keys
+    /// from the json as (`msg`), (`level`) for snazy.
     pub json_keys: Vec<String>,
 
+    #[arg(
+        long,
+        verbatim_doc_comment,
+        default_value = "{msg}",
+        env = "SNAZY_JSON_MSG_FORMAT"
+    )]
+    /// Set the format of the message of the logs parsed with --json-keys.
+    ///
+    /// Any key mapped with --json-keys can be used as a tag, e.g:
+    /// "{logger} {caller}: {msg}", the mapped keys not used are shown as
+    /// key=value after the message.
+    pub json_msg_format: String,
+
     #[arg(long, verbatim_doc_comment)]
     ///  A regexp to match an action on.
     ///
@@ -117,8 +242,96 @@ struct Args {
     ///  The command to run when a regexp match the --action-match
     pub action_command: Option<String>,
 
+    #[arg(long, verbatim_doc_comment)]
+    /// A rule running a command, as json. You can have multiple rules.
+    ///
+    /// The rule can match a regexp on the line, a level and a field of the json
+    /// record (`key` when it's present or `key=regexp`), all of them need to
+    /// match. The command can use {} for the match, {group} for a named group
+    /// of the regexp, {level}, {msg}, {ts}, {namespace}, {pod}, {container}
+    /// and {field.key} for a field of the json record. They are passed as
+    /// environment variables (e.g: `$SNAZY_MATCH`, `$SNAZY_FIELD_KEY`) and the
+    /// record on the stdin of the command. A rule can have a "debounce" (e.g:
+    /// "30s") to not run its command again before this time.
+    ///
+    /// For example:
+    ///
+    /// `--action '{"name": "notify", "level": "error", "field": "error", "command": "notify-send {pod} {field.error}"}'`
+    pub action: Vec<String>,
+
+    #[arg(long, verbatim_doc_comment, value_hint = ValueHint::FilePath)]
+    /// A json file with an array of rules like --action
+    pub action_file: Option<String>,
+
+    #[arg(long, default_value_t = 4, env = "SNAZY_ACTION_MAX_RUNNING")]
+    /// The maximum number of actions running at the same time, the matches
+    /// are skipped when reached
+    pub action_max_running: usize,
+
+    #[arg(long, value_parser = crate::verdict::parse_condition, verbatim_doc_comment)]
+    /// Exit with an error at the end if a record matches
+    ///
+    /// A level (e.g: error matches the error and fatal records) or a regexp on
+    /// the line. You can have multiple --fail-on.
+    pub fail_on: Vec<crate::verdict::Condition>,
+
+    #[arg(long, value_parser = crate::verdict::parse_condition, verbatim_doc_comment)]
+    /// Stop reading the logs after a record matches
+    ///
+    /// A level or a regexp on the line like --fail-on.
+    pub stop_on: Vec<crate::verdict::Condition>,
+
+    #[arg(short = 'm', long, verbatim_doc_comment)]
+    /// Stop reading the logs after this number of records matching --fail-on
+    /// or --stop-on, or after this number of records without them.
+    pub max_count: Option<usize>,
+
+    #[arg(
+        long,
+        verbatim_doc_comment,
+        value_name = "url",
+        conflicts_with = "files"
+    )]
+    /// Listen for logs on a socket instead of reading files or stdin.
+    ///
+    /// The url can be `udp://host:port`, `tcp://host:port` or
+    /// `unix:///path/to/socket`, for example:
+    ///
+    /// `snazy --listen udp://127.0.0.1:5514`
+    ///
+    /// Syslog messages are detected and their priority is used as the level.
+    pub listen: Option<String>,
+
+    #[arg(
+        long,
+        verbatim_doc_comment,
+        default_value = "{remote}",
+        env = "SNAZY_LISTEN_PREFIX_FORMAT"
+    )]
+    /// Set the format of the prefix of the logs received with --listen.
+    ///
+    /// The {remote} tag will be replaced by the address of the sender.
+    pub listen_prefix_format: String,
+
     #[arg(value_hint = ValueHint::FilePath)]
     files: Option<Vec<String>>,
+
+    #[arg(
+        last = true,
+        value_name = "command",
+        value_hint = ValueHint::CommandWithArguments,
+        conflicts_with_all = ["files", "listen"],
+        verbatim_doc_comment
+    )]
+    /// Run a command and show its output.
+    ///
+    /// The stdout and stderr of the command are read separately and the
+    /// lines coming from stderr are marked. The signals received by snazy are
+    /// forwarded to the command and snazy exit with the command exit code, for
+    /// example:
+    ///
+    /// `snazy -- kubectl logs -f deployment/controller`
+    command: Vec<String>,
 }
 
 fn regexp_colorize(regexps: &[String]) -> HashMap<String, Color> {
@@ -133,7 +346,7 @@ fn regexp_colorize(regexps: &[String]) -> HashMap<String, Color> {
     for (i, regexp) in regexps.iter().enumerate() {
         let defchosen = colours[i % colours.len()];
         let mut chosen = defchosen;
-        let mut reg = regexp.to_string();
+        let mut reg = regexp.clone();
         if let Some(colour) = regexp.split(':').next() {
             // if we have three commas then it's a rgb
             if colour.split(',').count() == 3 {
@@ -168,6 +381,16 @@ fn regexp_colorize(regexps: &[String]) -> HashMap<String, Color> {
     regexp_colours
 }
 
+fn time_format(format: String) -> String {
+    match format.as_str() {
+        "short" => String::from("%H:%M:%S"),
+        "ms" => String::from("%H:%M:%S%.3f"),
+        "iso" => String::from("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+        "full" => String::from("%Y-%m-%d %H:%M:%S%.9f %Z"),
+        _ => format,
+    }
+}
+
 fn colouring(color: ColorWhen) -> bool {
     match color {
         ColorWhen::Always => true,
@@ -176,18 +399,25 @@ fn colouring(color: ColorWhen) -> bool {
     }
 }
 
-/// Return a `HashMap` of a vector of splited by = string
-fn make_json_keys(json_keys: &[String]) -> HashMap<String, String> {
-    let ret: HashMap<String, String> = json_keys
+/// Return a `HashMap` of a vector of splited by = string, the values are json
+/// pointers and get their leading / added when missing.
+fn make_json_keys(json_keys: &[String]) -> Result<HashMap<String, String>, String> {
+    json_keys
         .iter()
-        .map(|s| {
-            let mut parts = s.splitn(2, '=');
-            let key = parts.next().unwrap().to_string();
-            let value = parts.next().unwrap().to_string();
-            (key, value)
+        .map(|s| match s.split_once('=') {
+            Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+                let value = if value.starts_with('/') {
+                    value.to_string()
+                } else {
+                    format!("/{value}")
+                };
+                Ok((key.to_string(), value))
+            }
+            _ => Err(format!(
+                "invalid json key {s}, it should be like key=/json/pointer"
+            )),
         })
-        .collect();
-    ret
+        .collect()
 }
 
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
@@ -203,30 +433,78 @@ pub fn build_cli_config() -> Config {
         std::process::exit(0)
     }
 
-    if !args.json_keys.is_empty() && args.json_keys.len() != 3 {
-        eprintln!("you should have multiple json-keys containning a match for the keys 'level', 'msg' and 'ts'");
-        std::process::exit(1);
-    }
-
     let regexp_colours = regexp_colorize(&args.regexp);
-    let colouring = colouring(args.color);
-    if !colouring {
+    if !colouring(args.color) {
         Paint::disable();
     }
-    let json_keys = make_json_keys(&args.json_keys);
+    let timezone = crate::utils::parse_timezone(&args.timezone).unwrap_or_else(|e| {
+        eprintln!("invalid timezone {}: {e}", args.timezone);
+        std::process::exit(1);
+    });
+    let actions = crate::actions::parse_rules(&args.action, args.action_file.as_deref())
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+    let json_keys = make_json_keys(&args.json_keys).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
 
     Config {
         level_symbols: args.level_symbols,
+        listen: args.listen,
+        listen_prefix_format: args.listen_prefix_format,
+        otel_prefix_format: args.otel_prefix_format,
         kail_prefix_format: args.kail_prefix_format,
         kail_no_prefix: args.kail_no_prefix,
-        time_format: args.time_format,
+        time_format: time_format(args.time_format),
+        timezone,
+        time_mode: args.time_mode,
+        gap: args.gap,
         skip_line_regexp: args.skip_line_regexp,
+        state: Mutex::default(),
+        tkn_prefix_format: args.tkn_prefix_format,
         filter_levels: args.filter_levels,
+        filter_steps: args.filter_steps,
+        filter_tasks: args.filter_tasks,
         action_command: args.action_command,
+        actions,
+        action_max_running: args.action_max_running,
+        fail_on: args.fail_on,
+        stop_on: args.stop_on,
+        max_count: args.max_count,
+        add_timestamp: args.add_timestamp,
+        summary: args.summary,
         action_regexp: args.action_regexp,
         files: args.files,
+        command: args.command,
+        compose_prefix_format: args.compose_prefix_format,
         regexp_colours,
-        colouring,
+        collapse_groups: args.collapse_groups,
         json_keys,
+        json_msg_format: args.json_msg_format,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_json_keys() {
+        let keys =
+            make_json_keys(&[String::from("msg=/foo"), String::from("caller=bar/0")]).unwrap();
+        assert_eq!(keys["msg"], "/foo");
+        assert_eq!(keys["caller"], "/bar/0");
+        assert!(make_json_keys(&[String::from("msg")]).is_err());
+        assert!(make_json_keys(&[String::from("=/foo")]).is_err());
+        assert!(make_json_keys(&[String::from("msg=")]).is_err());
+    }
+
+    #[test]
+    fn test_time_format() {
+        assert_eq!(time_format(String::from("ms")), "%H:%M:%S%.3f");
+        assert_eq!(time_format(String::from("%H:%M")), "%H:%M");
     }
 }
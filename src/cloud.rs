@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+/// A log entry of a cloud logging export, the line is parsed again with the
+/// snazy formats and the fallback values are used when the line doesn't have
+/// them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub line: String,
+    pub fallback: HashMap<String, String>,
+    /// the namespace, pod and container of the kubernetes resource
+    pub kail: Option<(String, String, String)>,
+}
+
+fn is_gcp_entry(value: &Value) -> bool {
+    ["textPayload", "jsonPayload", "protoPayload"]
+        .iter()
+        .any(|k| value.get(k).is_some())
+        && (value.get("insertId").is_some() || value.get("logName").is_some())
+}
+
+// an entry from `gcloud logging read --format=json`
+fn gcp_entry(value: &Value, time_format: &str) -> Entry {
+    let mut fallback = HashMap::new();
+    if let Some(severity) = value["severity"].as_str() {
+        let level = match severity {
+            "DEFAULT" | "NOTICE" => "INFO",
+            "CRITICAL" | "ALERT" | "EMERGENCY" => "FATAL",
+            severity => severity,
+        };
+        fallback.insert("level".to_string(), level.to_string());
+    }
+    if let Some(ts) = value.get("timestamp") {
+        fallback.insert(
+            "ts".to_string(),
+            crate::utils::convert_ts_float_or_str(ts, time_format),
+        );
+    }
+    let line = if let Some(text) = value["textPayload"].as_str() {
+        text.to_string()
+    } else {
+        let payload = value
+            .get("jsonPayload")
+            .or_else(|| value.get("protoPayload"))
+            .unwrap();
+        if let Some(message) = payload["message"].as_str() {
+            fallback.insert("msg".to_string(), message.to_string());
+        }
+        payload.to_string()
+    };
+    let labels = &value["resource"]["labels"];
+    let kail = labels["pod_name"].as_str().map(|pod| {
+        (
+            labels["namespace_name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            pod.to_string(),
+            labels["container_name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        )
+    });
+    Entry {
+        line,
+        fallback,
+        kail,
+    }
+}
+
+// an event from `aws logs filter-log-events` or `aws logs get-log-events`
+fn cloudwatch_entry(value: &Value, time_format: &str) -> Option<Entry> {
+    let message = value["message"].as_str()?;
+    let mut fallback = HashMap::new();
+    if let Some(ts) = value.get("timestamp") {
+        fallback.insert(
+            "ts".to_string(),
+            crate::utils::convert_ts_float_or_str(ts, time_format),
+        );
+    }
+    Some(Entry {
+        line: message.trim_end().to_string(),
+        fallback,
+        kail: None,
+    })
+}
+
+/// Get the entries of a JSON document exported from a cloud logging service:
+/// an array of log entries, the `CloudWatch` events or a single Google Cloud
+/// Logging entry. Returns None if that's not one of them or it has no entries,
+/// e.g: `[]`.
+pub fn entries(value: &Value, time_format: &str) -> Option<Vec<Entry>> {
+    let entries = match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => Some(
+            items
+                .iter()
+                .map(|item| {
+                    if is_gcp_entry(item) {
+                        gcp_entry(item, time_format)
+                    } else {
+                        Entry {
+                            line: item.to_string(),
+                            fallback: HashMap::new(),
+                            kail: None,
+                        }
+                    }
+                })
+                .collect(),
+        ),
+        Value::Object(_) if is_gcp_entry(value) => Some(vec![gcp_entry(value, time_format)]),
+        Value::Object(object) => {
+            let events = object.get("events")?.as_array()?;
+            events
+                .iter()
+                .map(|event| cloudwatch_entry(event, time_format))
+                .collect()
+        }
+        _ => None,
+    };
+    entries.filter(|entries: &Vec<Entry>| !entries.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcp_entries() {
+        let value: Value = serde_json::from_str(
+            r#"[
+  {
+    "insertId": "1",
+    "jsonPayload": {"message": "hello", "foo": "bar"},
+    "resource": {"type": "k8s_container", "labels": {"namespace_name": "ns", "pod_name": "pod", "container_name": "container"}},
+    "severity": "WARNING",
+    "timestamp": "2024-01-02T03:04:05.123456Z"
+  },
+  {
+    "insertId": "2",
+    "textPayload": "plain text",
+    "resource": {"type": "gce_instance", "labels": {}},
+    "severity": "DEFAULT",
+    "timestamp": "2024-01-02T03:04:06.123456Z"
+  }
+]"#,
+        )
+        .unwrap();
+        let entries = entries(&value, "%H:%M:%S").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line, r#"{"foo":"bar","message":"hello"}"#);
+        assert_eq!(entries[0].fallback["level"], "WARNING");
+        assert_eq!(entries[0].fallback["msg"], "hello");
+        assert_eq!(entries[0].fallback["ts"], "03:04:05");
+        assert_eq!(
+            entries[0].kail,
+            Some((
+                String::from("ns"),
+                String::from("pod"),
+                String::from("container")
+            ))
+        );
+        assert_eq!(entries[1].line, "plain text");
+        assert_eq!(entries[1].fallback["level"], "INFO");
+        assert_eq!(entries[1].kail, None);
+    }
+
+    #[test]
+    fn test_cloudwatch_entries() {
+        let value: Value = serde_json::from_str(
+            r#"{"events": [{"logStreamName": "s", "timestamp": 1650602040628, "message": "{\"level\":\"info\",\"msg\":\"hello\"}\n", "ingestionTime": 1650602040700, "eventId": "1"}], "searchedLogStreams": []}"#,
+        )
+        .unwrap();
+        let entries = entries(&value, "%H:%M:%S").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].line, r#"{"level":"info","msg":"hello"}"#);
+        assert_eq!(entries[0].fallback["ts"], "04:34:00");
+    }
+
+    #[test]
+    fn test_not_cloud_entries() {
+        for json in [r#"{"level":"info","msg":"hello"}"#, "[1, 2]", r#""hello""#] {
+            let value: Value = serde_json::from_str(json).unwrap();
+            assert_eq!(entries(&value, "%H:%M:%S"), None);
+        }
+    }
+}
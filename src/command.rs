@@ -0,0 +1,207 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use is_terminal::IsTerminal;
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+
+use crate::config::Config;
+use crate::parse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+// send every line of the child output to the channel, invalid utf8 is replaced
+fn forward_lines(output: impl Read, stream: Stream, tx: &Sender<(Stream, String)>) {
+    let mut reader = BufReader::new(output);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buf);
+                let line = line.trim_end_matches(['\n', '\r']).to_string();
+                if tx.send((stream, line)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// how long the command has to exit after a SIGTERM before we kill it
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Start a command in its own process group with its stdout and stderr piped.
+pub fn spawn_command(command: &[String]) -> io::Result<Child> {
+    let mut cmd = Command::new(&command[0]);
+    cmd.args(&command[1..])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // run the command in its own process group so a ctrl-c from the
+        // terminal only reach snazy which forward it.
+        .process_group(0);
+    if io::stdin().is_terminal() {
+        // reading the terminal from a background process group would stop it
+        cmd.stdin(Stdio::null());
+    }
+    cmd.spawn()
+}
+
+/// Send the stdout and stderr lines of the command to the channel, signals
+/// received by snazy are forwarded to the command. Returns the exit code of the
+/// command, or 128 + the signal number if it has been killed.
+pub fn run_command(mut child: Child, tx: &Sender<(Stream, String)>) -> io::Result<i32> {
+    let pid = Pid::from_raw(i32::try_from(child.id()).expect("a valid pid"));
+    let mut signals = Signals::new([SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2])?;
+    let handle = signals.handle();
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if let Ok(signal) = Signal::try_from(signal) {
+                let _ = killpg(pid, signal);
+            }
+        }
+    });
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let txout = tx.clone();
+    let txerr = tx.clone();
+    let readers = [
+        thread::spawn(move || forward_lines(stdout, Stream::Stdout, &txout)),
+        thread::spawn(move || forward_lines(stderr, Stream::Stderr, &txerr)),
+    ];
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let status = child.wait()?;
+    handle.close();
+    Ok(status
+        .code()
+        .unwrap_or_else(|| 128 + status.signal().unwrap_or_default()))
+}
+
+// stop the process group of the command, killing it if it doesn't exit in
+// time, the runner returns once the command is gone.
+fn terminate<T>(pid: Pid, runner: &JoinHandle<T>) {
+    let _ = killpg(pid, Signal::SIGTERM);
+    let start = Instant::now();
+    while !runner.is_finished() {
+        if start.elapsed() > TERMINATE_TIMEOUT {
+            let _ = killpg(pid, Signal::SIGKILL);
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+pub fn read_from_command(config: &Arc<Config>) {
+    let child = match spawn_command(&config.command) {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("cannot run {}: {e}", config.command[0]);
+            std::process::exit(127);
+        }
+    };
+    let pid = Pid::from_raw(i32::try_from(child.id()).expect("a valid pid"));
+    let (tx, rx) = mpsc::channel();
+    let runner = thread::spawn(move || run_command(child, &tx));
+
+    let stderr_prefix = crate::utils::stderr_prefix();
+    for (stream, line) in rx {
+        let prefix = match stream {
+            Stream::Stdout => "",
+            Stream::Stderr => stderr_prefix.as_str(),
+        };
+        if let Some(info) = parse::do_prefixed_line(config, prefix, &line) {
+            println!("{info}");
+        }
+        if crate::verdict::stopped(config) {
+            break;
+        }
+    }
+    if let Some(info) = parse::end_of_input(config) {
+        println!("{info}");
+    }
+
+    // the command may never write again, e.g: a quiet `kubectl logs -f`
+    let stopped = crate::verdict::stopped(config);
+    if stopped {
+        terminate(pid, &runner);
+    }
+    let code = match runner.join().unwrap() {
+        Ok(_) if stopped => 0,
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("cannot run {}: {e}", config.command[0]);
+            127
+        }
+    };
+    crate::summary::show(config);
+    let verdict = crate::verdict::summary(config);
+    std::process::exit(if code == 0 { verdict } else { code });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(command: &[&str]) -> (i32, Vec<(Stream, String)>) {
+        let command: Vec<String> = command.iter().map(ToString::to_string).collect();
+        let (tx, rx) = mpsc::channel();
+        let code = run_command(spawn_command(&command).unwrap(), &tx).unwrap();
+        drop(tx);
+        (code, rx.into_iter().collect())
+    }
+
+    #[test]
+    fn test_run_command_streams() {
+        let (code, lines) = run(&["sh", "-c", "echo out; echo err >&2"]);
+        assert_eq!(code, 0);
+        assert!(lines.contains(&(Stream::Stdout, String::from("out"))));
+        assert!(lines.contains(&(Stream::Stderr, String::from("err"))));
+    }
+
+    #[test]
+    fn test_run_command_exit_code() {
+        let (code, _) = run(&["sh", "-c", "exit 3"]);
+        assert_eq!(code, 3);
+        let (code, _) = run(&["sh", "-c", "kill -TERM $$"]);
+        assert_eq!(code, 128 + 15);
+    }
+
+    #[test]
+    fn test_run_command_not_found() {
+        assert!(spawn_command(&[String::from("/does/not/exist")]).is_err());
+    }
+
+    #[test]
+    fn test_terminate() {
+        for command in ["sleep 30", "trap '' TERM; sleep 30"] {
+            let child =
+                spawn_command(&[String::from("sh"), String::from("-c"), command.to_string()])
+                    .unwrap();
+            let pid = Pid::from_raw(i32::try_from(child.id()).unwrap());
+            let (tx, _rx) = mpsc::channel();
+            let runner = thread::spawn(move || run_command(child, &tx));
+            thread::sleep(Duration::from_millis(100));
+            let start = Instant::now();
+            terminate(pid, &runner);
+            let code = runner.join().unwrap().unwrap();
+            assert!(code == 128 + 15 || code == 128 + 9, "{command}: {code}");
+            assert!(start.elapsed() < TERMINATE_TIMEOUT * 2);
+        }
+    }
+}
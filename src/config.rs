@@ -1,8 +1,16 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
 
+use chrono::{DateTime, Duration, FixedOffset};
+use chrono_tz::Tz;
 use clap::ValueEnum;
 use yansi::Color;
 
+use crate::actions::Action;
+use crate::verdict::Condition;
+
 #[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
 pub enum LogLevel {
     Info,
@@ -22,6 +30,31 @@ pub fn level_from_str(level: &str) -> &'static LogLevel {
     }
 }
 
+/// the numeric levels of bunyan and pino (10 trace, 20 debug, 30 info, 40
+/// warn, 50 error, 60 fatal)
+pub fn level_from_number(level: u64) -> &'static LogLevel {
+    match level {
+        0..=20 => &LogLevel::Debug,
+        21..=30 => &LogLevel::Info,
+        31..=40 => &LogLevel::Warning,
+        41..=50 => &LogLevel::Error,
+        _ => &LogLevel::Fatal,
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self {
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Warning => "WARNING",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+        };
+        write!(f, "{level}")
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum)]
 pub enum ColorWhen {
     /// show colors if the output goes to an interactive console (default)
@@ -32,20 +65,85 @@ pub enum ColorWhen {
     Never,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum)]
+pub enum TimeMode {
+    /// the time of the record
+    Absolute,
+    /// the time since the first record
+    Relative,
+    /// the time since the previous record
+    Delta,
+}
+
+/// State kept between the lines while reading the logs
+#[derive(Debug, Default)]
+pub struct State {
+    /// partial container lines waiting for the rest of the line
+    pub partials: HashMap<String, String>,
+    /// the records matching --fail-on and the first one
+    pub failures: usize,
+    pub first_failure: Option<String>,
+    /// the records counted by --max-count
+    pub matches: usize,
+    /// why we stopped reading the logs with --stop-on or --max-count
+    pub stopped: Option<String>,
+    /// the actions still running
+    pub actions: Vec<JoinHandle<()>>,
+    /// the actions skipped by name while too many were running, they are
+    /// reported once there is room again or when we are done
+    pub skipped_actions: HashMap<String, usize>,
+    /// the json documents spread over multiple lines by prefix, e.g: the
+    /// stdout and stderr of the command or the clients of --listen
+    pub documents: HashMap<String, crate::parse::Document>,
+    /// the current job and step of the github action logs
+    pub gh_step: String,
+    /// if we are in a github action `##[group]`
+    pub gh_in_group: bool,
+    /// the time of the first and of the previous record
+    pub first_ts: Option<DateTime<FixedOffset>>,
+    pub previous_ts: Option<DateTime<FixedOffset>>,
+    /// the time between the previous record and the current one when more than --gap
+    pub gap: Option<Duration>,
+    /// the unix socket of --listen, removed when we exit
+    pub socket: Option<std::path::PathBuf>,
+    /// the counters of --summary
+    pub summary: crate::summary::Counters,
+}
+
 #[derive(Debug)]
 pub struct Config {
     pub action_command: Option<String>,
+    pub actions: Vec<Action>,
+    pub fail_on: Vec<Condition>,
+    pub stop_on: Vec<Condition>,
+    pub max_count: Option<usize>,
+    pub action_max_running: usize,
+    pub add_timestamp: bool,
+    pub summary: bool,
     pub action_regexp: Option<String>,
-    pub colouring: bool,
+    pub collapse_groups: bool,
+    pub command: Vec<String>,
+    pub compose_prefix_format: String,
     pub files: Option<Vec<String>>,
     pub filter_levels: Vec<LogLevel>,
+    pub filter_steps: Vec<String>,
+    pub filter_tasks: Vec<String>,
     pub json_keys: HashMap<String, String>,
+    pub json_msg_format: String,
     pub kail_no_prefix: bool,
     pub kail_prefix_format: String,
     pub level_symbols: bool,
+    pub listen: Option<String>,
+    pub listen_prefix_format: String,
+    pub otel_prefix_format: String,
     pub regexp_colours: HashMap<String, Color>,
     pub skip_line_regexp: Vec<String>,
+    pub state: Mutex<State>,
     pub time_format: String,
+    pub timezone: Tz,
+    pub time_mode: TimeMode,
+    pub gap: Option<Duration>,
+    pub tkn_prefix_format: String,
 }
 
 impl Default for Config {
@@ -55,14 +153,34 @@ impl Default for Config {
             kail_no_prefix: false,
             kail_prefix_format: String::from("{namespace}/{pod}[{container}]"),
             time_format: String::from("%H:%M:%S"),
-            colouring: false,
+            timezone: Tz::UTC,
+            time_mode: TimeMode::Absolute,
+            gap: None,
+            collapse_groups: false,
+            command: Vec::new(),
+            compose_prefix_format: String::from("{service}"),
             filter_levels: <Vec<LogLevel>>::new(),
+            filter_steps: Vec::new(),
+            filter_tasks: Vec::new(),
             regexp_colours: HashMap::new(),
             json_keys: HashMap::new(),
+            json_msg_format: String::from("{msg}"),
             level_symbols: bool::default(),
+            listen: None,
+            listen_prefix_format: String::from("{remote}"),
+            otel_prefix_format: String::from("{service.name}"),
             action_regexp: Some(String::new()),
             action_command: Some(String::new()),
+            actions: vec![],
+            fail_on: vec![],
+            stop_on: vec![],
+            max_count: None,
+            action_max_running: 4,
+            add_timestamp: false,
+            summary: false,
             skip_line_regexp: Vec::new(),
+            state: Mutex::default(),
+            tkn_prefix_format: String::from("[{task}:{step}]"),
         }
     }
 }
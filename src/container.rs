@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Deserialize;
+
+const CRI_RE: &str =
+    r"^(?P<time>\d{4}-\d{2}-\d{2}T\S+) (?P<stream>stdout|stderr) (?P<tag>[FP])(?: (?P<log>.*))?$";
+
+/// The log of a container with the envelope of the container runtime removed
+#[derive(Debug, PartialEq, Eq)]
+pub struct Envelope {
+    pub log: String,
+    pub stream: String,
+    pub time: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ContainerLine {
+    /// not a docker or CRI line, it should be parsed as is
+    NotContainer,
+    /// a partial line, kept until we get the rest of it
+    Partial,
+    Complete(Envelope),
+}
+
+// The docker json-file logging driver format
+#[derive(Deserialize, Debug)]
+struct Docker {
+    log: String,
+    stream: String,
+    time: String,
+}
+
+// append the partial log to what we had for this stream or return the full log
+fn reassemble(
+    partials: &mut HashMap<String, String>,
+    key: String,
+    log: &str,
+    partial: bool,
+) -> Option<String> {
+    if partial {
+        partials.entry(key).or_default().push_str(log);
+        return None;
+    }
+    Some(partials.remove(&key).unwrap_or_default() + log)
+}
+
+/// Unwrap a line from the docker json-file or the CRI log format, the partial
+/// lines are kept in `partials` by stream (with `source` to differentiate where
+/// the line comes from) until we get the final one.
+pub fn unwrap_line(
+    line: &str,
+    source: &str,
+    partials: &mut HashMap<String, String>,
+) -> ContainerLine {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    if let Ok(d) = serde_json::from_str::<Docker>(line) {
+        // docker split the long lines, only the last one has a newline
+        let partial = !d.log.ends_with('\n');
+        let key = format!("{source}{}", d.stream);
+        return match reassemble(partials, key, d.log.trim_end_matches('\n'), partial) {
+            Some(log) => ContainerLine::Complete(Envelope {
+                log,
+                stream: d.stream,
+                time: d.time,
+            }),
+            None => ContainerLine::Partial,
+        };
+    }
+
+    let reg = REG.get_or_init(|| Regex::new(CRI_RE).unwrap());
+    let Some(capture) = reg.captures(line) else {
+        return ContainerLine::NotContainer;
+    };
+    let stream = capture.name("stream").unwrap().as_str();
+    let log = capture.name("log").map(|l| l.as_str()).unwrap_or_default();
+    let partial = capture.name("tag").unwrap().as_str() == "P";
+    match reassemble(partials, format!("{source}{stream}"), log, partial) {
+        Some(log) => ContainerLine::Complete(Envelope {
+            log,
+            stream: stream.to_string(),
+            time: capture.name("time").unwrap().as_str().to_string(),
+        }),
+        None => ContainerLine::Partial,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_line() {
+        let mut partials = HashMap::new();
+        let line = r#"{"log":"{\"level\":\"info\",\"msg\":\"hello\"}\n","stream":"stderr","time":"2023-10-06T00:17:09.669794202Z"}"#;
+        assert_eq!(
+            unwrap_line(line, "", &mut partials),
+            ContainerLine::Complete(Envelope {
+                log: String::from(r#"{"level":"info","msg":"hello"}"#),
+                stream: String::from("stderr"),
+                time: String::from("2023-10-06T00:17:09.669794202Z"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_docker_partial_line() {
+        let mut partials = HashMap::new();
+        let line = r#"{"log":"hello ","stream":"stdout","time":"2023-10-06T00:17:09.669794202Z"}"#;
+        assert_eq!(unwrap_line(line, "", &mut partials), ContainerLine::Partial);
+        let line = r#"{"log":"moto\n","stream":"stdout","time":"2023-10-06T00:17:09.669794202Z"}"#;
+        let ContainerLine::Complete(envelope) = unwrap_line(line, "", &mut partials) else {
+            panic!("expected a complete line")
+        };
+        assert_eq!(envelope.log, "hello moto");
+        assert!(partials.is_empty());
+    }
+
+    #[test]
+    fn test_cri_lines() {
+        let mut partials = HashMap::new();
+        let line = "2023-10-06T00:17:09.669794202Z stdout F hello moto";
+        let ContainerLine::Complete(envelope) = unwrap_line(line, "", &mut partials) else {
+            panic!("expected a complete line")
+        };
+        assert_eq!(envelope.log, "hello moto");
+        assert_eq!(envelope.stream, "stdout");
+        assert_eq!(envelope.time, "2023-10-06T00:17:09.669794202Z");
+
+        let partial = "2023-10-06T00:17:09.669794202Z stderr P {\"level\":\"error\",";
+        let interleaved = "2023-10-06T00:17:09.669794202Z stdout F out";
+        let last = "2023-10-06T00:17:09.669794202Z stderr F \"msg\":\"boom\"}";
+        assert_eq!(
+            unwrap_line(partial, "", &mut partials),
+            ContainerLine::Partial
+        );
+        let ContainerLine::Complete(envelope) = unwrap_line(interleaved, "", &mut partials) else {
+            panic!("expected a complete line")
+        };
+        assert_eq!(envelope.log, "out");
+        let ContainerLine::Complete(envelope) = unwrap_line(last, "", &mut partials) else {
+            panic!("expected a complete line")
+        };
+        assert_eq!(envelope.log, r#"{"level":"error","msg":"boom"}"#);
+        assert_eq!(envelope.stream, "stderr");
+    }
+
+    #[test]
+    fn test_not_container_line() {
+        let mut partials = HashMap::new();
+        for line in [
+            r#"{"level":"info","msg":"hello"}"#,
+            "hello moto",
+            "2023-10-06 stdout F hello",
+        ] {
+            assert_eq!(
+                unwrap_line(line, "", &mut partials),
+                ContainerLine::NotContainer
+            );
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use yansi::Paint;
+
+/// get a field with its dotted name, ECS fields can be dotted keys
+/// (`{"log.level": "info"}`), nested objects (`{"log": {"level": "info"}}`) or
+/// a mix of both.
+pub fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    let object = value.as_object()?;
+    if let Some(v) = object.get(key) {
+        return Some(v);
+    }
+    key.match_indices('.').find_map(|(i, _)| {
+        object
+            .get(&key[..i])
+            .and_then(|nested| lookup(nested, &key[i + 1..]))
+    })
+}
+
+fn lookup_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    lookup(value, key).and_then(Value::as_str)
+}
+
+/// Parse the logs of the logback logstash encoder (`@timestamp`, `level`,
+/// `message`, `logger_name`, `stack_trace`) and of the Elastic Common Schema
+/// (`@timestamp`, `log.level`, `message`, `service.name`,
+/// `error.stack_trace`).
+pub fn parse_ecs(line: &str, time_format: &str) -> Option<HashMap<String, String>> {
+    let value = serde_json::from_str::<Value>(line).ok()?;
+    let timestamp = lookup_str(&value, "@timestamp")?;
+    let message = lookup_str(&value, "message")?;
+    let level = lookup_str(&value, "log.level").or_else(|| lookup_str(&value, "level"))?;
+    let level = match level.to_lowercase().as_str() {
+        "warn" => "WARNING".to_string(),
+        "trace" => "DEBUG".to_string(),
+        _ => level.to_uppercase(),
+    };
+
+    let mut text = message.trim().to_string();
+    if let Some(name) = lookup_str(&value, "service.name")
+        .or_else(|| lookup_str(&value, "log.logger"))
+        .or_else(|| lookup_str(&value, "logger_name"))
+    {
+        text = format!("{} {text}", Paint::blue(name));
+    }
+    if let Some(stack_trace) =
+        lookup_str(&value, "error.stack_trace").or_else(|| lookup_str(&value, "stack_trace"))
+    {
+        text.push_str(&crate::utils::indented_block(stack_trace));
+    }
+
+    let mut msg = HashMap::new();
+    msg.insert("msg".to_string(), text);
+    msg.insert("level".to_string(), level);
+    msg.insert(
+        "ts".to_string(),
+        crate::utils::convert_str_to_ts(timestamp, time_format),
+    );
+    Some(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let value: Value =
+            serde_json::from_str(r#"{"log.level":"info","log":{"origin.file":{"name":"a.java"}}}"#)
+                .unwrap();
+        assert_eq!(lookup(&value, "log.level"), Some(&Value::from("info")));
+        assert_eq!(
+            lookup(&value, "log.origin.file.name"),
+            Some(&Value::from("a.java"))
+        );
+        assert_eq!(lookup(&value, "log.missing"), None);
+    }
+
+    #[test]
+    fn test_logstash_encoder() {
+        let line = r#"{"@timestamp":"2024-01-02T03:04:05.123+01:00","@version":"1","message":"hello","logger_name":"com.acme.App","thread_name":"main","level":"WARN","level_value":30000,"stack_trace":"java.lang.IllegalStateException: boom\n\tat com.acme.App.main(App.java:42)"}"#;
+        let msg = parse_ecs(line, "%H:%M:%S").unwrap();
+        assert_eq!(msg["level"], "WARNING");
+        assert_eq!(msg["ts"], "02:04:05");
+        assert!(msg["msg"].starts_with(&format!("{} hello\n    ", Paint::blue("com.acme.App"))));
+        assert!(msg["msg"].contains("IllegalStateException"));
+        assert_eq!(msg["msg"].lines().count(), 3);
+    }
+
+    #[test]
+    fn test_ecs_dotted_and_nested() {
+        for line in [
+            r#"{"@timestamp":"2024-01-02T03:04:05.123Z","log.level":"error","message":"hello","service.name":"checkout","ecs.version":"1.6.0"}"#,
+            r#"{"@timestamp":"2024-01-02T03:04:05.123Z","log":{"level":"error"},"message":"hello","service":{"name":"checkout"},"ecs":{"version":"8.0.0"}}"#,
+        ] {
+            let msg = parse_ecs(line, "%H:%M:%S").unwrap();
+            assert_eq!(msg["level"], "ERROR");
+            assert_eq!(msg["ts"], "03:04:05");
+            assert_eq!(msg["msg"], format!("{} hello", Paint::blue("checkout")));
+        }
+    }
+
+    #[test]
+    fn test_not_ecs() {
+        assert!(parse_ecs(r#"{"level":"info","msg":"hello"}"#, "%H:%M:%S").is_none());
+        assert!(parse_ecs(
+            r#"{"@timestamp":"2024-01-02T03:04:05Z","message":"hello"}"#,
+            "%H:%M:%S"
+        )
+        .is_none());
+    }
+}
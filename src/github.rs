@@ -0,0 +1,126 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use yansi::Paint;
+
+// the lines of `gh run view --log` are prefixed by the job, the step and the
+// timestamp, separated by tabs. The timestamp may start with a BOM.
+const GH_RE: &str = r"^(?P<job>[^\t]+)\t(?P<step>[^\t]+)\t\x{feff}?(?P<time>\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?Z) ?(?P<line>.*)";
+const COMMAND_RE: &str =
+    r"^##\[(?P<command>group|endgroup|error|warning|notice|debug)\](?P<text>.*)";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GhLine {
+    pub job: String,
+    pub step: String,
+    pub time: String,
+    pub line: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Group {
+    Start(String),
+    End,
+}
+
+/// parse a line from `gh run view --log`
+pub fn parse_line(line: &str) -> Option<GhLine> {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    let reg = REG.get_or_init(|| Regex::new(GH_RE).unwrap());
+    let capture = reg.captures(line)?;
+    Some(GhLine {
+        job: capture.name("job").unwrap().as_str().to_string(),
+        step: capture.name("step").unwrap().as_str().to_string(),
+        time: capture.name("time").unwrap().as_str().to_string(),
+        line: capture.name("line").unwrap().as_str().to_string(),
+    })
+}
+
+fn workflow_command(line: &str) -> Option<(String, String)> {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    let reg = REG.get_or_init(|| Regex::new(COMMAND_RE).unwrap());
+    let capture = reg.captures(line)?;
+    Some((
+        capture.name("command").unwrap().as_str().to_string(),
+        capture.name("text").unwrap().as_str().trim().to_string(),
+    ))
+}
+
+/// the `##[group]` and `##[endgroup]` commands
+pub fn group(line: &str) -> Option<Group> {
+    match workflow_command(line)? {
+        (command, title) if command == "group" => Some(Group::Start(title)),
+        (command, _) if command == "endgroup" => Some(Group::End),
+        _ => None,
+    }
+}
+
+/// the `##[error]`, `##[warning]`, `##[notice]` and `##[debug]` annotations
+/// as a level and a message
+pub fn annotation(line: &str) -> Option<(String, String)> {
+    let (command, text) = workflow_command(line)?;
+    let level = match command.as_str() {
+        "error" => "ERROR",
+        "warning" => "WARNING",
+        "notice" => "INFO",
+        "debug" => "DEBUG",
+        _ => return None,
+    };
+    Some((level.to_string(), text))
+}
+
+pub fn step_header(job: &str, step: &str) -> String {
+    Paint::new(format!("━━ {job} › {step}")).bold().to_string()
+}
+
+pub fn group_header(title: &str, collapsed: bool) -> String {
+    let arrow = if collapsed { "▶" } else { "▼" };
+    Paint::blue(format!("{arrow} {title}")).bold().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let line = "build\tRun tests\t\u{feff}2023-11-20T10:01:02.1234567Z ##[group]Run make test";
+        assert_eq!(
+            parse_line(line),
+            Some(GhLine {
+                job: String::from("build"),
+                step: String::from("Run tests"),
+                time: String::from("2023-11-20T10:01:02.1234567Z"),
+                line: String::from("##[group]Run make test"),
+            })
+        );
+        assert_eq!(parse_line("2023-11-20T10:01:02.1234567Z hello"), None);
+    }
+
+    #[test]
+    fn test_group() {
+        assert_eq!(
+            group("##[group]Run make test"),
+            Some(Group::Start(String::from("Run make test")))
+        );
+        assert_eq!(group("##[endgroup]"), Some(Group::End));
+        assert_eq!(group("##[error]boom"), None);
+        assert_eq!(group("hello"), None);
+    }
+
+    #[test]
+    fn test_annotation() {
+        assert_eq!(
+            annotation("##[error]Process completed with exit code 2."),
+            Some((
+                String::from("ERROR"),
+                String::from("Process completed with exit code 2.")
+            ))
+        );
+        assert_eq!(
+            annotation("##[warning]deprecated"),
+            Some((String::from("WARNING"), String::from("deprecated")))
+        );
+        assert_eq!(annotation("##[group]title"), None);
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{TcpListener, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use yansi::Paint;
+
+use crate::config::Config;
+use crate::parse;
+
+/// A line received from a socket with the address of who sent it
+pub type Received = (String, String);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ListenAddr {
+    Udp(String),
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+pub enum Listener {
+    Udp(UdpSocket),
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// parse a listen url like `udp://127.0.0.1:5514`, `tcp://[::1]:5514` or `unix:///tmp/snazy.sock`
+pub fn parse_listen_url(url: &str) -> Result<ListenAddr, String> {
+    let Some((scheme, addr)) = url.split_once("://") else {
+        return Err(format!(
+            "invalid listen url {url}, expected udp://, tcp:// or unix://"
+        ));
+    };
+    if addr.is_empty() {
+        return Err(format!("missing address in listen url {url}"));
+    }
+    match scheme {
+        "udp" => Ok(ListenAddr::Udp(addr.to_string())),
+        "tcp" => Ok(ListenAddr::Tcp(addr.to_string())),
+        #[cfg(unix)]
+        "unix" => Ok(ListenAddr::Unix(PathBuf::from(addr))),
+        _ => Err(format!("unsupported listen scheme {scheme} in {url}")),
+    }
+}
+
+pub fn bind(addr: &ListenAddr) -> io::Result<Listener> {
+    match addr {
+        ListenAddr::Udp(a) => Ok(Listener::Udp(UdpSocket::bind(a)?)),
+        ListenAddr::Tcp(a) => Ok(Listener::Tcp(TcpListener::bind(a)?)),
+        #[cfg(unix)]
+        ListenAddr::Unix(path) => {
+            // remove a stale socket left over by a previous run, but never
+            // another file
+            if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+            Ok(Listener::Unix(UnixListener::bind(path)?))
+        }
+    }
+}
+
+// send every line of a stream connection to the channel
+fn forward_lines(stream: impl Read, remote: &str, tx: &Sender<Received>) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if tx.send((remote.to_string(), line)).is_err() {
+            break;
+        }
+    }
+}
+
+impl Listener {
+    /// Spawn the threads receiving the logs, every received line is sent to the
+    /// channel with the remote address.
+    pub fn spawn(self, tx: Sender<Received>) {
+        match self {
+            Listener::Udp(socket) => {
+                thread::spawn(move || {
+                    let mut buf = vec![0; 65536];
+                    while let Ok((size, remote)) = socket.recv_from(&mut buf) {
+                        let datagram = String::from_utf8_lossy(&buf[..size]);
+                        for line in datagram.lines() {
+                            if tx.send((remote.to_string(), line.to_string())).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+            Listener::Tcp(listener) => {
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        let remote = stream
+                            .peer_addr()
+                            .map(|a| a.to_string())
+                            .unwrap_or_default();
+                        let tx = tx.clone();
+                        thread::spawn(move || forward_lines(stream, &remote, &tx));
+                    }
+                });
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                thread::spawn(move || {
+                    for (count, stream) in listener.incoming().flatten().enumerate() {
+                        // unix clients are usually unnamed, number the connections instead
+                        let remote = stream
+                            .peer_addr()
+                            .ok()
+                            .and_then(|a| a.as_pathname().map(|p| p.display().to_string()))
+                            .unwrap_or_else(|| format!("unix:{count}"));
+                        let tx = tx.clone();
+                        thread::spawn(move || forward_lines(stream, &remote, &tx));
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Remove the unix socket we listen on, when we exit
+pub fn remove_socket(config: &Config) {
+    if let Some(path) = config.state.lock().unwrap().socket.take() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+pub fn read_from_listener(config: &Arc<Config>) {
+    let url = config.listen.as_ref().unwrap();
+    let listener = parse_listen_url(url)
+        .and_then(|addr| {
+            let listener = bind(&addr).map_err(|e| e.to_string())?;
+            #[cfg(unix)]
+            if let ListenAddr::Unix(path) = addr {
+                config.state.lock().unwrap().socket = Some(path);
+            }
+            Ok(listener)
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("cannot listen on {url}: {e}");
+            std::process::exit(1);
+        });
+    let (tx, rx) = mpsc::channel();
+    listener.spawn(tx);
+
+    for (remote, line) in rx {
+        let prefix = config.listen_prefix_format.replace("{remote}", &remote);
+        let prefix = Paint::magenta(prefix).to_string();
+        if let Some(info) = parse::do_prefixed_line(config, &prefix, &line) {
+            println!("{info}");
+        }
+        if crate::verdict::stopped(config) {
+            break;
+        }
+    }
+    remove_socket(config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_listen_url() {
+        assert_eq!(
+            parse_listen_url("udp://127.0.0.1:5514"),
+            Ok(ListenAddr::Udp(String::from("127.0.0.1:5514")))
+        );
+        assert_eq!(
+            parse_listen_url("tcp://[::1]:5514"),
+            Ok(ListenAddr::Tcp(String::from("[::1]:5514")))
+        );
+        assert_eq!(
+            parse_listen_url("unix:///tmp/snazy.sock"),
+            Ok(ListenAddr::Unix(PathBuf::from("/tmp/snazy.sock")))
+        );
+        assert!(parse_listen_url("http://localhost").is_err());
+        assert!(parse_listen_url("127.0.0.1:5514").is_err());
+        assert!(parse_listen_url("udp://").is_err());
+    }
+
+    #[test]
+    fn test_listen_udp() {
+        let listener = bind(&ListenAddr::Udp(String::from("127.0.0.1:0"))).unwrap();
+        let Listener::Udp(ref socket) = listener else {
+            panic!("expected an udp listener")
+        };
+        let addr = socket.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        listener.spawn(tx);
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.send_to(b"hello\nmoto", addr).unwrap();
+        let (remote, line) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(remote, client.local_addr().unwrap().to_string());
+        assert_eq!(line, "hello");
+        let (_, line) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(line, "moto");
+    }
+
+    #[test]
+    fn test_listen_tcp() {
+        let listener = bind(&ListenAddr::Tcp(String::from("127.0.0.1:0"))).unwrap();
+        let Listener::Tcp(ref socket) = listener else {
+            panic!("expected a tcp listener")
+        };
+        let addr = socket.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        listener.spawn(tx);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"{\"level\":\"info\",\"msg\":\"hello\"}\n")
+            .unwrap();
+        let (remote, line) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(remote, client.local_addr().unwrap().to_string());
+        assert_eq!(line, r#"{"level":"info","msg":"hello"}"#);
+    }
+
+    #[test]
+    fn test_listen_unix_keeps_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snazy.sock");
+        drop(bind(&ListenAddr::Unix(path.clone())).unwrap());
+        // the stale socket of a previous run is replaced
+        assert!(bind(&ListenAddr::Unix(path.clone())).is_ok());
+
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, "keep me").unwrap();
+        assert!(bind(&ListenAddr::Unix(file.clone())).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+    }
+}
@@ -2,23 +2,76 @@
 #![allow(clippy::too_many_lines)]
 #![allow(clippy::cast_possible_truncation)]
 #![allow(clippy::map_unwrap_or)]
+#![allow(clippy::struct_excessive_bools)]
 #![forbid(unsafe_code)]
 
 use std::sync::Arc;
+use std::thread;
 
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::config::Config;
+
+mod access;
+mod actions;
+mod clef;
 mod cli;
+mod cloud;
+mod command;
 mod config;
+mod container;
+mod ecs;
+mod github;
+mod listen;
+mod otel;
 mod parse;
+mod summary;
+mod tracing;
 mod utils;
+mod verdict;
+mod webhook;
 
 #[cfg(test)]
 mod parse_test;
 
+// remove the socket of --listen and show the --summary when we are interrupted
+// with a ctrl-c, it is done from a thread and not from the signal handler.
+fn on_interrupt(config: &Arc<Config>) {
+    if !config.summary && config.listen.is_none() {
+        return;
+    }
+    let Ok(mut signals) = Signals::new([SIGINT, SIGTERM]) else {
+        return;
+    };
+    let config = Arc::clone(config);
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            listen::remove_socket(&config);
+            summary::show(&config);
+            std::process::exit(128 + signal);
+        }
+    });
+}
+
 fn main() {
-    let config = cli::build_cli_config();
-    if config.files.is_some() {
-        parse::read_from_files(&Arc::new(config));
+    let config = Arc::new(cli::build_cli_config());
+    // the command gets the ctrl-c and we show the summary when it exits
+    if config.command.is_empty() {
+        on_interrupt(&config);
+    }
+    if !config.command.is_empty() {
+        command::read_from_command(&config);
+    } else if config.listen.is_some() {
+        listen::read_from_listener(&config);
+    } else if config.files.is_some() {
+        parse::read_from_files(&config);
     } else {
-        parse::read_from_stdin(&Arc::new(config));
+        parse::read_from_stdin(&config);
+    }
+    summary::show(&config);
+    let code = verdict::summary(&config);
+    if code != 0 {
+        std::process::exit(code);
     }
 }
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// OpenTelemetry logs as exported in OTLP JSON, e.g: by the file exporter of
+// the collector.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Batch {
+    resource_logs: Vec<ResourceLogs>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ResourceLogs {
+    #[serde(default)]
+    resource: Resource,
+    #[serde(default)]
+    scope_logs: Vec<ScopeLogs>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Resource {
+    #[serde(default)]
+    attributes: Vec<KeyValue>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ScopeLogs {
+    #[serde(default)]
+    log_records: Vec<LogRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LogRecord {
+    time_unix_nano: Option<Value>,
+    observed_time_unix_nano: Option<Value>,
+    severity_number: Option<u8>,
+    severity_text: Option<String>,
+    body: Option<Value>,
+    #[serde(default)]
+    attributes: Vec<KeyValue>,
+    trace_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct KeyValue {
+    key: String,
+    value: Value,
+}
+
+/// a log record of the batch as a json line snazy understands and its prefix
+#[derive(Debug, PartialEq, Eq)]
+pub struct Record {
+    pub prefix: String,
+    pub line: String,
+}
+
+/// convert an OTLP `AnyValue` to a string
+fn any_value(value: &Value) -> String {
+    let Some((kind, value)) = value.as_object().and_then(|o| o.iter().next()) else {
+        return String::new();
+    };
+    match (kind.as_str(), value) {
+        (_, Value::String(s)) => s.clone(),
+        ("arrayValue", _) => value["values"]
+            .as_array()
+            .map(|values| values.iter().map(any_value).collect::<Vec<_>>().join(","))
+            .map(|values| format!("[{values}]"))
+            .unwrap_or_default(),
+        ("kvlistValue", _) => value["values"]
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .map(|kv| {
+                        format!(
+                            "{}={}",
+                            kv["key"].as_str().unwrap_or_default(),
+                            any_value(&kv["value"])
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default(),
+        _ => value.to_string(),
+    }
+}
+
+/// map the OpenTelemetry severity numbers to our levels, 1-4 is TRACE and
+/// 21-24 FATAL.
+pub fn severity_level(number: Option<u8>, text: Option<&str>) -> String {
+    match number {
+        Some(1..=8) => "DEBUG".to_string(),
+        Some(9..=12) => "INFO".to_string(),
+        Some(13..=16) => "WARNING".to_string(),
+        Some(17..=20) => "ERROR".to_string(),
+        Some(21..=24) => "FATAL".to_string(),
+        _ => text.map_or_else(|| "INFO".to_string(), str::to_uppercase),
+    }
+}
+
+// the nanoseconds can be a string or a number in OTLP JSON
+fn unix_nano(value: Option<&Value>) -> Option<Value> {
+    match value? {
+        Value::String(s) => s.parse::<u64>().ok().filter(|n| *n > 0).map(Value::from),
+        Value::Number(n) if n.as_u64() != Some(0) => Some(Value::Number(n.clone())),
+        _ => None,
+    }
+}
+
+/// Unpack an OTLP JSON batch to its log records, the resource attributes can be
+/// used in the prefix format, e.g: `{service.name}`.
+pub fn unpack(line: &str, prefix_format: &str) -> Option<Vec<Record>> {
+    let batch = serde_json::from_str::<Batch>(line).ok()?;
+    let placeholder = Regex::new(r"\{(?P<key>[^}]+)\}").unwrap();
+    let mut records = Vec::new();
+    for resource_logs in batch.resource_logs {
+        let attributes: BTreeMap<String, String> = resource_logs
+            .resource
+            .attributes
+            .iter()
+            .map(|kv| (kv.key.clone(), any_value(&kv.value)))
+            .collect();
+        let prefix = placeholder
+            .replace_all(prefix_format, |c: &regex::Captures| {
+                attributes.get(&c["key"]).cloned().unwrap_or_default()
+            })
+            .trim()
+            .to_string();
+
+        for record in resource_logs.scope_logs.iter().flat_map(|s| &s.log_records) {
+            let mut msg = record.body.as_ref().map(any_value).unwrap_or_default();
+            let mut fields: Vec<String> = record
+                .attributes
+                .iter()
+                .map(|kv| format!("{}={}", kv.key, any_value(&kv.value)))
+                .collect();
+            if let Some(trace_id) = record.trace_id.as_ref().filter(|t| !t.is_empty()) {
+                fields.push(format!("trace_id={trace_id}"));
+            }
+            if !fields.is_empty() {
+                msg = format!("{msg} {}", fields.join(" "));
+            }
+            let mut line = json!({
+                "level": severity_level(record.severity_number, record.severity_text.as_deref()),
+                "msg": msg,
+            });
+            if let Some(ts) = unix_nano(record.time_unix_nano.as_ref())
+                .or_else(|| unix_nano(record.observed_time_unix_nano.as_ref()))
+            {
+                line["ts"] = ts;
+            }
+            records.push(Record {
+                prefix: prefix.clone(),
+                line: line.to_string(),
+            });
+        }
+    }
+    Some(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BATCH: &str = r#"{"resourceLogs":[{"resource":{"attributes":[{"key":"service.name","value":{"stringValue":"checkout"}},{"key":"k8s.pod.name","value":{"stringValue":"checkout-1"}}]},"scopeLogs":[{"scope":{"name":"app"},"logRecords":[{"timeUnixNano":"1650602040628962500","severityNumber":17,"severityText":"Error","body":{"stringValue":"payment failed"},"attributes":[{"key":"order","value":{"intValue":"42"}}],"traceId":"5b8efff798038103d269b633813fc60c"},{"observedTimeUnixNano":1650602041000000000,"severityText":"warn","body":{"kvlistValue":{"values":[{"key":"a","value":{"boolValue":true}}]}}}]}]}]}"#;
+
+    #[test]
+    fn test_unpack() {
+        let records = unpack(BATCH, "{service.name}/{k8s.pod.name}{missing}").unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].prefix, "checkout/checkout-1");
+        let first: Value = serde_json::from_str(&records[0].line).unwrap();
+        assert_eq!(first["level"], "ERROR");
+        assert_eq!(
+            first["msg"],
+            "payment failed order=42 trace_id=5b8efff798038103d269b633813fc60c"
+        );
+        assert_eq!(first["ts"], 1_650_602_040_628_962_500_u64);
+        let second: Value = serde_json::from_str(&records[1].line).unwrap();
+        assert_eq!(second["level"], "WARN");
+        assert_eq!(second["msg"], "a=true");
+        assert_eq!(second["ts"], 1_650_602_041_000_000_000_u64);
+    }
+
+    #[test]
+    fn test_severity_level() {
+        assert_eq!(severity_level(Some(1), None), "DEBUG");
+        assert_eq!(severity_level(Some(9), Some("whatever")), "INFO");
+        assert_eq!(severity_level(Some(13), None), "WARNING");
+        assert_eq!(severity_level(Some(24), None), "FATAL");
+        assert_eq!(severity_level(None, Some("Error")), "ERROR");
+        assert_eq!(severity_level(None, None), "INFO");
+    }
+
+    #[test]
+    fn test_not_otel() {
+        assert!(unpack(r#"{"level":"info","msg":"hello"}"#, "{service.name}").is_none());
+    }
+}
@@ -1,20 +1,38 @@
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::{self, BufRead};
-use std::process::Command;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
+use chrono::DateTime;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use yansi::{Color, Paint, Style};
 
+use crate::cloud;
 use crate::config;
-use crate::config::Config;
+use crate::config::{Config, TimeMode};
+use crate::container::{self, ContainerLine};
+use crate::github::{self, Group};
+use crate::otel;
 
 const KAIL_RE: &str = r"^(?P<namespace>[^/]*)/(?P<pod>[^\[]*)\[(?P<container>[^]]*)]: (?P<line>.*)";
+const COMPOSE_RE: &str = r"^(?P<service>[\w.-]+[-_]\d+)\s+\| ?(?P<line>.*)";
+const TKN_RE: &str = r"^\[(?P<task>[^\]:]+?) : (?P<step>[^\]]+?)\] (?P<line>.*)";
+const SYSLOG_RE: &str = r"^<(?P<pri>\d{1,3})>(?:1 (?P<ts>\S+) \S+ (?P<app>\S+) \S+ \S+ (?:-|\[.*?\]) ?|(?P<bsdts>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) \S+ (?P<tag>[^:\[\s]+)(?:\[\d+\])?: ?)?(?P<msg>.*)";
+
+/// The regexp of the kail lines, compiled once as it is matched on every line
+pub(crate) fn kail_regex() -> &'static Regex {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    REG.get_or_init(|| Regex::new(KAIL_RE).unwrap())
+}
+
+// the biggest json document spread over multiple lines we keep, a bigger one
+// is shown line by line
+const MAX_DOCUMENT: usize = 16 * 1024 * 1024;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Pac {
@@ -26,36 +44,119 @@ struct Pac {
     other: BTreeMap<String, Value>,
 }
 
+// the level is a number with bunyan and pino
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+enum Level {
+    Name(String),
+    Number(u64),
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct Knative {
-    level: String,
+    level: Level,
     msg: String,
     #[serde(flatten)]
     other: BTreeMap<String, Value>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Info {
     level: String,
     msg: String,
     timestamp: String,
     others: String,
+    /// lines shown before the log, e.g: a section header
+    headers: Vec<String>,
+    /// a line we could not parse, printed as is
+    raw: Option<String>,
+}
+
+impl Info {
+    // only print some headers, nothing if there is none
+    fn from_headers(mut headers: Vec<String>) -> Option<Info> {
+        let last = headers.pop()?;
+        Some(Info {
+            headers,
+            raw: Some(last),
+            ..Info::default()
+        })
+    }
+}
+
+/// A json document spread over multiple lines we are reading
+#[derive(Debug, Default)]
+pub struct Document {
+    lines: Vec<String>,
+    size: usize,
+    // the arrays and objects still open
+    depth: usize,
+}
+
+impl Document {
+    // add a line, returns true when the document is complete
+    fn push(&mut self, line: &str) -> bool {
+        // a json string can't have a newline, it ends on the same line
+        let mut in_string = false;
+        let mut escaped = false;
+        for c in line.chars() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                '[' | '{' if !in_string => self.depth += 1,
+                ']' | '}' if !in_string => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        self.size += line.len();
+        self.lines.push(line.to_string());
+        self.depth == 0
+    }
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for header in &self.headers {
+            writeln!(f, "{header}")?;
+        }
+        if let Some(raw) = &self.raw {
+            return write!(f, "{raw}");
+        }
+        write!(
+            f,
+            "{} {} {}{}",
+            self.level, self.timestamp, self.others, self.msg
+        )
+    }
 }
 
+/// Parse a line, the ts is kept as an UTC RFC3339 timestamp until the record is
+/// shown.
 pub fn extract_info(rawline: &str, config: &Config) -> HashMap<String, String> {
-    let time_format = config.time_format.as_str();
+    // the timestamps are shown with the timezone and format asked
+    let time_format = crate::utils::TS_FORMAT;
     let mut msg = HashMap::new();
     let mut kail_msg_prefix = String::new();
     let mut line = rawline.to_string();
 
     if let Some(prefix) = parse_kail_lines(config, rawline) {
-        let replacer = Regex::new(KAIL_RE).unwrap();
-        line = replacer.replace_all(rawline, "$line").to_string();
-        kail_msg_prefix = prefix;
+        line = kail_regex().replace_all(rawline, "$line").to_string();
+        if !config.kail_no_prefix {
+            kail_msg_prefix = Paint::blue(prefix).to_string();
+        }
     }
 
-    if !config.json_keys.is_empty() {
-        msg = custom_json_match(config, time_format, &kail_msg_prefix, line.as_str());
+    // a json payload is parsed as any other line, the syslog header fills in
+    // what the record lacks, e.g: the app or the ts
+    let mut syslog_header = HashMap::new();
+    if let Some(syslog) = parse_syslog_line(line.as_str(), time_format) {
+        if syslog["msg"].starts_with('{') {
+            line.clone_from(&syslog["msg"]);
+            syslog_header = syslog;
+        } else {
+            msg = syslog;
+        }
     }
 
     if let Ok(p) = serde_json::from_str::<Pac>(line.as_str()) {
@@ -64,7 +165,7 @@ pub fn extract_info(rawline: &str, config: &Config) -> HashMap<String, String> {
         // parse timestamp to a unix timestamp
         msg.insert(
             "ts".to_string(),
-            crate::utils::convert_str_to_ts(p.timestamp.as_str(), config.time_format.as_str()),
+            crate::utils::convert_str_to_ts(p.timestamp.as_str(), time_format),
         );
         let mut others = String::new();
         if p.other.contains_key("provider") {
@@ -79,112 +180,572 @@ pub fn extract_info(rawline: &str, config: &Config) -> HashMap<String, String> {
 
     if let Ok(p) = serde_json::from_str::<Knative>(line.as_str()) {
         msg.insert("msg".to_string(), p.msg.trim().to_string());
-        msg.insert("level".to_string(), p.level.to_uppercase());
-        if let Some(ts) = p.other.get("ts") {
+        let level = match p.level {
+            // slog custom levels are relative to a level, e.g: INFO+2
+            Level::Name(level) => match level.rsplit_once(['+', '-']) {
+                Some((name, offset)) if offset.parse::<u8>().is_ok() => name.to_uppercase(),
+                _ => level.to_uppercase(),
+            },
+            Level::Number(level) => config::level_from_number(level).to_string(),
+        };
+        msg.insert("level".to_string(), level);
+        if let Some(ts) = p.other.get("ts").or_else(|| p.other.get("time")) {
             msg.insert(
                 String::from("ts"),
                 crate::utils::convert_ts_float_or_str(ts, time_format),
             );
-        };
+        }
+    }
+
+    if let Some(clef) = crate::clef::parse_clef(line.as_str(), time_format) {
+        msg = clef;
+    }
+
+    if let Some(tracing) = crate::tracing::parse_tracing(line.as_str(), time_format) {
+        msg = tracing;
+    }
+
+    if let Some(ecs) = crate::ecs::parse_ecs(line.as_str(), time_format) {
+        msg = ecs;
+    }
+
+    // the json access logs only when nothing else parsed the record
+    if msg.is_empty() {
+        if let Some(access) = crate::access::parse_access(line.as_str(), time_format) {
+            msg = access;
+        }
+    }
+
+    if !config.json_keys.is_empty() {
+        // the mapped keys win over the ones we found, the others are only used
+        // when we found nothing
+        for (key, value) in custom_json_match(config, time_format, line.as_str()) {
+            if config.json_keys.contains_key(&key) {
+                msg.insert(key, value);
+            } else {
+                msg.entry(key).or_insert(value);
+            }
+        }
+        if msg.contains_key("msg") {
+            let text = format_json_msg(config, &msg);
+            msg.insert("msg".to_string(), text);
+        }
     }
 
-    if !config.kail_no_prefix && !kail_msg_prefix.is_empty() && msg.contains_key("msg") {
-        *msg.get_mut("msg").unwrap() = format!("{} {}", Paint::blue(kail_msg_prefix), msg["msg"]);
+    for (key, value) in syslog_header {
+        msg.entry(key).or_insert(value);
+    }
+
+    if !kail_msg_prefix.is_empty() && msg.contains_key("msg") {
+        *msg.get_mut("msg").unwrap() = format!("{kail_msg_prefix} {}", msg["msg"]);
     }
     msg
 }
 
+// show the timestamp of a record, we keep the time of the records shown for the
+// relative times and the gaps between them
+fn show_ts(config: &Config, ts: &str) -> String {
+    let Ok(current) = DateTime::parse_from_rfc3339(ts) else {
+        return ts.to_string();
+    };
+    let mut state = config.state.lock().unwrap();
+    let first = *state.first_ts.get_or_insert(current);
+    let previous = state.previous_ts.replace(current).unwrap_or(current);
+    if config.gap.is_some_and(|gap| current - previous > gap) {
+        state.gap = Some(current - previous);
+    }
+    match config.time_mode {
+        TimeMode::Absolute => crate::utils::display_ts(ts, &config.time_format, config.timezone),
+        TimeMode::Relative => crate::utils::format_duration(current - first),
+        TimeMode::Delta => crate::utils::format_duration(current - previous),
+    }
+}
+
+// the time we read a record without a timestamp with --add-timestamp, dimmed
+// to not be mistaken with the time of the record. It is not a time of the logs
+// so it doesn't count for the relative and delta times or the gaps.
+fn ingestion_ts(config: &Config) -> Option<String> {
+    if !config.add_timestamp {
+        return None;
+    }
+    let now = chrono::Utc::now()
+        .format(crate::utils::TS_FORMAT)
+        .to_string();
+    let now = crate::utils::display_ts(&now, &config.time_format, config.timezone);
+    Some(Paint::fixed(13, now).dimmed().italic().to_string())
+}
+
+// a separator before the record when it came long after the previous one
+fn gap_header(config: &Config) -> Option<String> {
+    let gap = config.state.lock().unwrap().gap.take()?;
+    let length = crate::utils::format_duration(gap);
+    Some(
+        Paint::new(format!("──── {} ────", length.trim_start_matches('+')))
+            .dimmed()
+            .to_string(),
+    )
+}
+
+/// parse a RFC5424 or RFC3164 syslog line, the level is taken from the severity
+/// of the priority.
+fn parse_syslog_line(line: &str, time_format: &str) -> Option<HashMap<String, String>> {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    let reg = REG.get_or_init(|| Regex::new(SYSLOG_RE).unwrap());
+    let capture = reg.captures(line)?;
+    let pri = capture.name("pri")?.as_str().parse::<u8>().ok()?;
+    let level = match pri % 8 {
+        0..=2 => "FATAL",
+        3 => "ERROR",
+        4 => "WARNING",
+        7 => "DEBUG",
+        _ => "INFO",
+    };
+    let mut msg = HashMap::new();
+    msg.insert("level".to_string(), level.to_string());
+    msg.insert(
+        "msg".to_string(),
+        capture.name("msg").unwrap().as_str().trim().to_string(),
+    );
+    if let Some(ts) = capture.name("ts").filter(|t| t.as_str() != "-") {
+        msg.insert(
+            "ts".to_string(),
+            crate::utils::convert_str_to_ts(ts.as_str(), time_format),
+        );
+    } else if let Some(ts) = capture.name("bsdts") {
+        msg.insert(
+            "ts".to_string(),
+            crate::utils::convert_bsd_ts(ts.as_str(), time_format),
+        );
+    }
+    if let Some(app) = capture
+        .name("app")
+        .or_else(|| capture.name("tag"))
+        .filter(|a| a.as_str() != "-")
+    {
+        msg.insert("others".to_string(), format!("{} ", app.as_str()));
+    }
+    Some(msg)
+}
+
 fn parse_kail_lines(config: &Config, rawline: &str) -> Option<String> {
-    let reg = Regex::new(KAIL_RE).unwrap();
+    let reg = kail_regex();
     if !reg.is_match(rawline) {
         return None;
     }
-    let mut kail_msg_prefix = config.kail_prefix_format.clone();
     let capture = reg.captures(rawline).unwrap();
     let namespace = capture.name("namespace").unwrap().as_str();
     let pod = capture.name("pod").unwrap().as_str();
     let container = capture.name("container").unwrap().as_str();
-    kail_msg_prefix = kail_msg_prefix
+    Some(kail_prefix(config, namespace, pod, container))
+}
+
+fn kail_prefix(config: &Config, namespace: &str, pod: &str, container: &str) -> String {
+    config
+        .kail_prefix_format
         .replace("{namespace}", namespace)
         .replace("{pod}", pod)
         .replace("{container}", container)
+        .replace("\\n", "\n")
+}
+
+/// parse the `service-1  | ` prefix added by `docker compose logs`, returns the
+/// coloured prefix and the line without it.
+fn parse_compose_lines(config: &Config, rawline: &str) -> Option<(String, String)> {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    let reg = REG.get_or_init(|| Regex::new(COMPOSE_RE).unwrap());
+    let capture = reg.captures(rawline)?;
+    let service = capture.name("service").unwrap().as_str();
+    let prefix = config
+        .compose_prefix_format
+        .replace("{service}", service)
         .replace("\\n", "\n");
-    Some(kail_msg_prefix)
+    let prefix = Paint::new(prefix)
+        .fg(crate::utils::colour_for(service))
+        .to_string();
+    Some((prefix, capture.name("line").unwrap().as_str().to_string()))
 }
 
-fn custom_json_match(
-    config: &Config,
-    time_format: &str,
-    kali_msg_prefix: &str,
-    line: &str,
-) -> HashMap<String, String> {
+/// parse the `[task : step] ` prefix of `tkn pr logs`, returns the task, the
+/// step and the line without the prefix.
+fn parse_tkn_lines(rawline: &str) -> Option<(String, String, String)> {
+    static REG: OnceLock<Regex> = OnceLock::new();
+    let reg = REG.get_or_init(|| Regex::new(TKN_RE).unwrap());
+    let capture = reg.captures(rawline)?;
+    Some((
+        capture.name("task").unwrap().as_str().to_string(),
+        capture.name("step").unwrap().as_str().to_string(),
+        capture.name("line").unwrap().as_str().to_string(),
+    ))
+}
+
+// the keys used when they are not mapped with --json-keys
+const DEFAULT_JSON_KEYS: [(&str, &str); 7] = [
+    ("msg", "/msg"),
+    ("msg", "/message"),
+    ("level", "/level"),
+    ("level", "/severity"),
+    ("ts", "/ts"),
+    ("ts", "/time"),
+    ("ts", "/timestamp"),
+];
+
+fn custom_json_match(config: &Config, time_format: &str, line: &str) -> HashMap<String, String> {
     let mut dico = HashMap::new();
     if let Ok(p) = serde_json::from_str::<Value>(line) {
+        for (key, pointer) in DEFAULT_JSON_KEYS {
+            if config.json_keys.contains_key(key) || dico.contains_key(key) {
+                continue;
+            }
+            let value = match (key, p.pointer(pointer)) {
+                (_, None) => continue,
+                ("ts", Some(v)) => crate::utils::convert_ts_float_or_str(v, time_format),
+                ("level", Some(Value::String(v))) => v.to_uppercase(),
+                (_, Some(Value::String(v))) => v.clone(),
+                (_, Some(v)) => v.to_string(),
+            };
+            dico.insert(key.to_string(), value);
+        }
         for (key, value) in &config.json_keys {
             if p.pointer(value).is_some() {
                 // if value  equal ts or timestamp or date then parse as timestamp
                 if key == "ts" || key == "timestamp" || key == "date" {
                     // make a serde json Value
                     let v = p.pointer(value).unwrap();
-                    let ts = crate::utils::convert_ts_float_or_str(v, time_format);
-                    dico.insert(key.to_string(), ts);
+                    let mut ts = crate::utils::convert_ts_float_or_str(v, time_format);
+                    // only the ts is shown when the record is shown
+                    if key != "ts" {
+                        ts = crate::utils::display_ts(&ts, &config.time_format, config.timezone);
+                    }
+                    dico.insert(key.clone(), ts);
                 } else {
                     let mut v = p.pointer(value).unwrap().to_string();
                     if v.contains('"') {
                         v = v.replace('"', "");
                     }
 
-                    dico.insert(key.to_string(), v);
+                    dico.insert(key.clone(), v);
                 }
             }
         }
     }
-    if !config.kail_no_prefix && !kali_msg_prefix.is_empty() && dico.contains_key("msg") {
-        *dico.get_mut("msg").unwrap() = format!("{} {}", Paint::blue(kali_msg_prefix), dico["msg"]);
-    }
     dico
 }
 
-pub fn action_on_regexp(config: &Config, line: &str) {
-    let reg = Regex::new(config.action_regexp.as_ref().unwrap()).unwrap();
-    if let Some(reg) = reg.captures(line) {
-        let regexpmatch = reg.get(0).unwrap().as_str();
-        // replace {} by the actual match
-        let action_command = config
-            .action_command
-            .as_ref()
-            .unwrap()
-            .replace("{}", regexpmatch);
-        if Command::new("sh")
-            .arg("-c")
-            .arg(action_command)
-            .spawn()
-            .is_ok()
-        {
-            println!("Spawned command: for action: {}", Paint::cyan(regexpmatch));
+// render the message with --json-msg-format, the mapped keys which are not in
+// the format are shown after the message
+fn format_json_msg(config: &Config, msg: &HashMap<String, String>) -> String {
+    let mut text = config.json_msg_format.clone();
+    let mut extras = Vec::new();
+    for key in config.json_keys.keys() {
+        let tag = format!("{{{key}}}");
+        let value = msg.get(key).map_or("", String::as_str);
+        if text.contains(&tag) {
+            text = text.replace(&tag, value);
+        } else if !["msg", "level", "ts"].contains(&key.as_str()) && !value.is_empty() {
+            extras.push(format!("{}={value}", Paint::new(key).dimmed()));
         }
     }
+    let mut text = text.replace("{msg}", &msg["msg"]).trim().to_string();
+    extras.sort();
+    for extra in extras {
+        text = format!("{text} {extra}");
+    }
+    text
+}
+
+// the action of --action-regexp and --action-command
+pub fn action_on_regexp(config: &Config, line: &str) {
+    let rule = crate::actions::Rule {
+        regexp: config.action_regexp.clone(),
+        command: config.action_command.clone().unwrap_or_default(),
+        ..crate::actions::Rule::default()
+    };
+    if let Ok(action) = crate::actions::Action::new(rule) {
+        crate::actions::trigger(config, &[action], line, &HashMap::new());
+    }
 }
 
 pub fn do_line(config: &Config, line: &str) -> Option<Info> {
+    do_prefixed_line(config, "", line)
+}
+
+/// Parse a line and add a prefix to the message, the prefix is shown as well
+/// for the non json lines. The prefix is printed as is and need to be styled by
+/// the caller.
+pub fn do_prefixed_line(config: &Config, prefix: &str, line: &str) -> Option<Info> {
+    // a json document spread over multiple lines, e.g: the output of gcloud or
+    // aws, one by prefix as the lines of the sources can be interleaved
+    let document = config.state.lock().unwrap().documents.remove(prefix);
+    let mut document = match document {
+        Some(document) => document,
+        None if line.trim() == "[" || line.trim() == "{" => Document::default(),
+        None => return process_line(config, prefix, line, HashMap::new()),
+    };
+    // the lines of a pretty printed document are indented until its end
+    if !document.lines.is_empty()
+        && !line.starts_with(char::is_whitespace)
+        && !line.starts_with([']', '}'])
+    {
+        // that wasn't a json document, show what we kept and continue
+        document.lines.push(line.to_string());
+        return do_lines(config, prefix, &document.lines);
+    }
+    if !document.push(line) {
+        if document.size > MAX_DOCUMENT {
+            // too big to keep it, show it as it is
+            return do_lines(config, prefix, &document.lines);
+        }
+        config
+            .state
+            .lock()
+            .unwrap()
+            .documents
+            .insert(prefix.to_string(), document);
+        return None;
+    }
+    match serde_json::from_str::<Value>(&document.lines.join("\n")) {
+        Ok(value) => do_json_document(config, prefix, &value),
+        Err(_) => do_lines(config, prefix, &document.lines),
+    }
+}
+
+// parse the lines one by one
+fn do_lines(config: &Config, prefix: &str, lines: &[String]) -> Option<Info> {
+    let lines = lines
+        .iter()
+        .filter_map(|line| process_line(config, prefix, line, HashMap::new()))
+        .map(|info| info.to_string())
+        .collect();
+    Info::from_headers(lines)
+}
+
+/// Show a json document, the logs exported from the cloud logging services are
+/// parsed entry by entry.
+fn do_json_document(config: &Config, prefix: &str, value: &Value) -> Option<Info> {
+    let Some(entries) = cloud::entries(value, crate::utils::TS_FORMAT) else {
+        return process_line(config, prefix, &value.to_string(), HashMap::new());
+    };
+    let lines = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let mut prefix = prefix.to_string();
+            if let Some((namespace, pod, container)) = &entry.kail {
+                if !config.kail_no_prefix {
+                    let kail = Paint::blue(kail_prefix(config, namespace, pod, container));
+                    prefix = format!("{prefix} {kail}").trim_start().to_string();
+                }
+            }
+            process_line(config, &prefix, &entry.line, entry.fallback)
+        })
+        .map(|info| info.to_string())
+        .collect();
+    Info::from_headers(lines)
+}
+
+/// Show the json documents we were reading when the input stops, if any.
+pub fn end_of_input(config: &Config) -> Option<Info> {
+    crate::actions::wait(config);
+    let mut documents: Vec<(String, Document)> =
+        std::mem::take(&mut config.state.lock().unwrap().documents)
+            .into_iter()
+            .collect();
+    documents.sort_by(|a, b| a.0.cmp(&b.0));
+    let lines = documents
+        .into_iter()
+        .flat_map(|(_, document)| document.lines)
+        .collect();
+    Info::from_headers(lines)
+}
+
+// the `fallback` values (level, ts and msg) are used when the line doesn't have
+// them, e.g: the time of the container runtime.
+fn process_line(
+    config: &Config,
+    prefix: &str,
+    line: &str,
+    mut fallback: HashMap<String, String>,
+) -> Option<Info> {
     // exclude lines with only space or empty
     if line.trim().is_empty() {
         return None;
     }
 
+    let mut line = line;
+    let mut prefix = prefix.to_string();
+    let compose = parse_compose_lines(config, line);
+    if let Some((service_prefix, service_line)) = &compose {
+        line = service_line.as_str();
+        prefix = format!("{prefix} {service_prefix}")
+            .trim_start()
+            .to_string();
+    }
+
+    let tkn = parse_tkn_lines(line);
+    if let Some((task, step, tkn_line)) = &tkn {
+        if (!config.filter_tasks.is_empty() && !config.filter_tasks.contains(task))
+            || (!config.filter_steps.is_empty() && !config.filter_steps.contains(step))
+        {
+            return None;
+        }
+        line = tkn_line.as_str();
+        let tkn_prefix = config
+            .tkn_prefix_format
+            .replace("{task}", task)
+            .replace("{step}", step)
+            .replace("\\n", "\n");
+        let tkn_prefix = Paint::new(tkn_prefix).fg(crate::utils::colour_for(task));
+        prefix = format!("{prefix} {tkn_prefix}").trim_start().to_string();
+    }
+
+    let envelope = {
+        let mut state = config.state.lock().unwrap();
+        match container::unwrap_line(line, &prefix, &mut state.partials) {
+            ContainerLine::Partial => return None,
+            ContainerLine::NotContainer => None,
+            ContainerLine::Complete(envelope) => Some(envelope),
+        }
+    };
+    if let Some(envelope) = &envelope {
+        line = envelope.log.as_str();
+        fallback.insert(
+            "ts".to_string(),
+            crate::utils::convert_str_to_ts(&envelope.time, crate::utils::TS_FORMAT),
+        );
+        if envelope.stream == "stderr" {
+            prefix = format!("{prefix} {}", crate::utils::stderr_prefix())
+                .trim_start()
+                .to_string();
+        }
+    }
+
+    // an OpenTelemetry batch, every log record is parsed as its own line
+    if let Some(records) = otel::unpack(line, &config.otel_prefix_format) {
+        let lines = records
+            .iter()
+            .filter_map(|record| {
+                let mut prefix = prefix.clone();
+                if !record.prefix.is_empty() {
+                    let service =
+                        Paint::new(&record.prefix).fg(crate::utils::colour_for(&record.prefix));
+                    prefix = format!("{prefix} {service}").trim_start().to_string();
+                }
+                do_prefixed_line(config, &prefix, &record.line)
+            })
+            .map(|info| info.to_string())
+            .collect();
+        return Info::from_headers(lines);
+    }
+
+    if line.starts_with(['[', '{']) {
+        if let Ok(value) = serde_json::from_str::<Value>(line) {
+            if cloud::entries(&value, crate::utils::TS_FORMAT).is_some() {
+                return do_json_document(config, &prefix, &value);
+            }
+        }
+    }
+
+    let mut headers = Vec::new();
+    let ghline = github::parse_line(line);
+    if let Some(gh) = &ghline {
+        line = gh.line.as_str();
+        fallback.insert(
+            "ts".to_string(),
+            crate::utils::convert_str_to_ts(&gh.time, crate::utils::TS_FORMAT),
+        );
+        let mut state = config.state.lock().unwrap();
+        let step = format!("{}\t{}", gh.job, gh.step);
+        if state.gh_step != step {
+            headers.push(github::step_header(&gh.job, &gh.step));
+            state.gh_step = step;
+            state.gh_in_group = false;
+        }
+        match github::group(line) {
+            Some(Group::Start(title)) => {
+                state.gh_in_group = true;
+                headers.push(github::group_header(&title, config.collapse_groups));
+                return Info::from_headers(headers);
+            }
+            Some(Group::End) => {
+                state.gh_in_group = false;
+                return Info::from_headers(headers);
+            }
+            None => {
+                // annotations are still shown in a collapsed group
+                if config.collapse_groups && state.gh_in_group && github::annotation(line).is_none()
+                {
+                    return Info::from_headers(headers);
+                }
+            }
+        }
+    }
+
     if config.action_regexp.is_some() {
         action_on_regexp(config, line);
     }
 
-    let msg = extract_info(line, config);
-    let unwrapped = serde_json::to_string(&msg).unwrap();
-    //check if unwrapped is not an empty hashmap
-    if unwrapped == "{}" {
-        println!(
-            "{}",
-            apply_regexps(&config.regexp_colours, line.to_string())
-        );
-        return None;
+    let mut msg = extract_info(line, config);
+    if msg.is_empty() && ghline.is_some() {
+        if let Some((level, text)) = github::annotation(line) {
+            msg.insert("level".to_string(), level);
+            msg.insert("msg".to_string(), text);
+        }
+    }
+    // a line we can't parse but we know its level, e.g: a google cloud textPayload
+    if msg.is_empty() && fallback.contains_key("level") {
+        let text = fallback.remove("msg").unwrap_or_else(|| line.to_string());
+        msg.insert("msg".to_string(), text);
+    }
+
+    // we need at least a message, e.g: --json-keys may only have found a ts
+    if !msg.contains_key("msg") {
+        crate::verdict::check(config, line, None);
+        crate::actions::trigger(config, &config.actions, line, &HashMap::new());
+        crate::summary::count_raw(config, line, fallback.get("ts").map(String::as_str));
+        let mut line = apply_regexps(&config.regexp_colours, line.to_string());
+        if !prefix.is_empty() {
+            line = format!("{prefix} {line}");
+        }
+        if let Some(ts) = fallback.get("ts") {
+            line = format!("{} {line}", Paint::fixed(13, show_ts(config, ts)));
+        } else if let Some(ts) = ingestion_ts(config) {
+            line = format!("{ts} {line}");
+        }
+        headers.extend(gap_header(config));
+        return Some(Info {
+            headers,
+            raw: Some(line),
+            ..Info::default()
+        });
+    }
+
+    if let Some(ts) = fallback.remove("ts") {
+        msg.entry("ts".to_string()).or_insert(ts);
+    }
+    for (key, value) in fallback {
+        msg.entry(key).or_insert(value);
+    }
+    msg.entry("level".to_string()).or_default();
+
+    crate::verdict::check(config, line, Some(msg["level"].as_str()));
+    // the fields for the actions and the --summary
+    let mut fields = HashMap::new();
+    if !config.actions.is_empty() || config.summary {
+        fields.clone_from(&msg);
+        if let Some(ts) = fields.get_mut("ts") {
+            *ts = crate::utils::display_ts(ts, &config.time_format, config.timezone);
+        }
+        // the kail prefix is only for showing the message
+        if let Some(prefix) = parse_kail_lines(config, line).filter(|_| !config.kail_no_prefix) {
+            let prefix = format!("{} ", Paint::blue(prefix));
+            if let Some(text) = fields.get_mut("msg") {
+                *text = text.trim_start_matches(&prefix).to_string();
+            }
+        }
+        crate::actions::trigger(config, &config.actions, line, &fields);
+    }
+
+    if !prefix.is_empty() && msg.contains_key("msg") {
+        *msg.get_mut("msg").unwrap() = format!("{prefix} {}", msg["msg"]);
     }
 
     if config
@@ -195,6 +756,7 @@ pub fn do_line(config: &Config, line: &str) -> Option<Info> {
         .count()
         > 0
     {
+        crate::summary::count_skipped(config);
         return None;
     }
 
@@ -203,9 +765,16 @@ pub fn do_line(config: &Config, line: &str) -> Option<Info> {
             .filter_levels
             .contains(config::level_from_str(&msg["level"].to_lowercase()))
     {
+        crate::summary::count_skipped(config);
         return None;
     }
 
+    crate::summary::count_record(config, line, &fields, msg.get("ts").map(String::as_str));
+    // only the records shown count for the relative times and the gaps
+    if let Some(ts) = msg.get_mut("ts") {
+        *ts = show_ts(config, ts);
+    }
+
     let mut level = crate::utils::color_by_level(msg.get("level").unwrap());
     if config.level_symbols {
         level = crate::utils::level_symbols(msg.get("level").unwrap());
@@ -213,22 +782,27 @@ pub fn do_line(config: &Config, line: &str) -> Option<Info> {
     let mut ts = String::new();
     if msg.contains_key("ts") {
         ts = Paint::fixed(13, msg.get("ts").unwrap()).to_string();
+    } else if let Some(now) = ingestion_ts(config) {
+        ts = now;
     }
     let other = if msg.contains_key("others") {
         format!(" {}", Paint::cyan(msg.get("others").unwrap()).italic())
     } else {
         String::new()
     };
-    let mut themsg = msg.get("msg").unwrap().to_string();
+    let mut themsg = msg.get("msg").unwrap().clone();
 
     if !config.regexp_colours.is_empty() {
         themsg = apply_regexps(&config.regexp_colours, themsg);
     }
+    headers.extend(gap_header(config));
     Some(Info {
         level,
         timestamp: ts,
         others: other,
         msg: themsg,
+        headers,
+        raw: None,
     })
 }
 
@@ -250,11 +824,14 @@ pub fn read_from_stdin(config: &Arc<Config>) {
         let parseline = &line.unwrap();
 
         if let Some(info) = do_line(config, parseline) {
-            println!(
-                "{} {} {}{}",
-                info.level, info.timestamp, info.others, info.msg
-            );
+            println!("{info}");
         }
+        if crate::verdict::stopped(config) {
+            break;
+        }
+    }
+    if let Some(info) = end_of_input(config) {
+        println!("{info}");
     }
 }
 
@@ -269,14 +846,15 @@ pub fn read_a_file(config: &Config, filename: &str, writeto: &mut dyn io::Write)
         let parseline = &line.unwrap();
 
         if let Some(info) = do_line(config, parseline) {
-            writeln!(
-                writeto,
-                "{} {} {}{}",
-                info.level, info.timestamp, info.others, info.msg
-            )
-            .unwrap();
+            writeln!(writeto, "{info}").unwrap();
+        }
+        if crate::verdict::stopped(config) {
+            break;
         }
     }
+    if let Some(info) = end_of_input(config) {
+        writeln!(writeto, "{info}").unwrap();
+    }
 }
 
 // read from a bunch files and pass read_from_stdin to stdout
@@ -287,5 +865,8 @@ pub fn read_from_files(config: &Arc<Config>) {
         let stdout = stdout.lock();
         let mut stdout = io::BufWriter::new(stdout);
         read_a_file(config, filename, &mut stdout);
+        if crate::verdict::stopped(config) {
+            break;
+        }
     }
 }
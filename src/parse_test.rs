@@ -5,10 +5,10 @@ mod tests {
     use std::{thread, vec};
 
     use regex::Regex;
-    use yansi::Color;
+    use yansi::{Color, Paint};
 
     use crate::config::Config;
-    use crate::parse::{action_on_regexp, do_line, extract_info};
+    use crate::parse::{action_on_regexp, do_line, do_prefixed_line, extract_info};
 
     #[test]
     fn test_get_line() {
@@ -125,6 +125,25 @@ mod tests {
         assert_eq!(info.get("level").unwrap(), "info");
     }
 
+    #[test]
+    fn test_config_json_keys_defaults() {
+        let mut keys = HashMap::new();
+        keys.insert(String::from("msg"), String::from("/foo"));
+        keys.insert(String::from("trace_id"), String::from("/trace/id"));
+
+        let config = Config {
+            json_keys: keys,
+            json_msg_format: String::from("[{trace_id}] {msg}"),
+            ..Config::default()
+        };
+        let line =
+            r#"{"foo": "Bar", "severity": "warning", "time": 1650602040, "trace": {"id": "abc"}}"#;
+        let info = extract_info(line, &config);
+        assert_eq!(info.get("msg").unwrap(), "[abc] Bar");
+        assert_eq!(info.get("level").unwrap(), "WARNING");
+        assert_eq!(info.get("ts").unwrap(), "2022-04-22T04:34:00Z");
+    }
+
     #[test]
     fn test_config_json_timestamp_float() {
         let mut keys = HashMap::new();
@@ -136,7 +155,14 @@ mod tests {
         };
         let line = r#"{"bar": 1650602040.6289625}"#;
         let info = extract_info(line, &config);
-        assert_eq!(info.get("ts").unwrap(), "04:34:00");
+        assert_eq!(info.get("ts").unwrap(), "2022-04-22T04:34:00.628962500Z");
+    }
+
+    #[test]
+    fn test_config_json_timestamp_float_or_str() {
+        let line = r#"{"level":"info","msg":"hello","ts":"1650602040.6289625"}"#;
+        let info = extract_info(line, &Config::default());
+        assert_eq!(info.get("ts").unwrap(), "2022-04-22T04:34:00.628962500Z");
     }
 
     #[test]
@@ -153,13 +179,13 @@ mod tests {
         let line =
             r#"{"bar": "2022-04-22T04:34:00.628550164Z", "foo": "hello", "level": "lelevel"}"#;
         let info = extract_info(line, &config);
-        assert_eq!(info.get("ts").unwrap(), "04:34:00");
+        assert_eq!(info.get("ts").unwrap(), "2022-04-22T04:34:00.628550164Z");
         assert_eq!(info.get("msg").unwrap(), "hello");
         assert_eq!(info.get("level").unwrap(), "lelevel");
 
         let line = r#"{"bar": 1650992726.6289625, "foo": "hello", "level": "lelevel"}"#;
         let info = extract_info(line, &config);
-        assert_eq!(info.get("ts").unwrap(), "17:05:26");
+        assert_eq!(info.get("ts").unwrap(), "2022-04-26T17:05:26.628962500Z");
     }
 
     #[test]
@@ -176,7 +202,7 @@ mod tests {
             ),
             ..Config::default()
         };
-        let line = r#"un HELLO MOTO nono el petiot roboto"#;
+        let line = "un HELLO MOTO nono el petiot roboto";
         action_on_regexp(&config, line);
         // sleep for a bit to let the file be created
         thread::sleep(core::time::Duration::from_millis(50));
@@ -186,6 +212,230 @@ mod tests {
         assert_eq!(contents, "you said HELLO MOTO\n");
     }
 
+    #[test]
+    fn test_action_rules() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let file_path = file.path().to_path_buf();
+        file.close().unwrap();
+
+        let rule = format!(
+            r#"{{"level":"error","field":"error.code=time","command":"echo {{pod}} {{msg}} {{field.error.code}} > {}"}}"#,
+            file_path.to_str().unwrap()
+        );
+        let config = Config {
+            actions: crate::actions::parse_rules(&[rule], None).unwrap(),
+            ..Config::default()
+        };
+        do_line(
+            &config,
+            r#"ns/web-1[app]: {"level":"info","msg":"fine","error":{"code":"timeout"}}"#,
+        );
+        do_line(
+            &config,
+            r#"ns/web-1[app]: {"level":"error","msg":"boom","error":{"code":"timeout"}}"#,
+        );
+        thread::sleep(core::time::Duration::from_millis(50));
+        let mut file = std::fs::File::open(file_path).unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "web-1 boom timeout\n");
+    }
+
+    #[test]
+    fn test_syslog_lines() {
+        let config = Config::default();
+        let line = "<165>1 2003-10-11T22:14:15.003Z mymachine evntslog - ID47 - hello moto";
+        let msg = extract_info(line, &config);
+        assert_eq!(msg["level"], "INFO");
+        assert_eq!(msg["ts"], "2003-10-11T22:14:15.003Z");
+        assert_eq!(msg["others"], "evntslog ");
+        assert_eq!(msg["msg"], "hello moto");
+
+        let line = "<11>Oct 11 22:14:15 mymachine su[123]: 'su root' failed";
+        let msg = extract_info(line, &config);
+        assert_eq!(msg["level"], "ERROR");
+        assert_eq!(msg["others"], "su ");
+        assert_eq!(msg["msg"], "'su root' failed");
+
+        let msg = extract_info("<12>just a message", &config);
+        assert_eq!(msg["level"], "WARNING");
+        assert_eq!(msg["msg"], "just a message");
+
+        let line = r#"<11>1 2003-10-11T22:14:15.003Z host app - - - {"level":"error","msg":"x"}"#;
+        let msg = extract_info(line, &config);
+        assert_eq!(msg["level"], "ERROR");
+        assert_eq!(msg["ts"], "2003-10-11T22:14:15.003Z");
+        assert_eq!(msg["others"], "app ");
+        assert_eq!(msg["msg"], "x");
+    }
+
+    #[test]
+    fn test_prefixed_info() {
+        let line = r#"{"level":"INFO","msg":"hello"}"#;
+        let info = do_prefixed_line(&Config::default(), "127.0.0.1:4242", line).unwrap();
+        assert!(info.to_string().contains("127.0.0.1:4242"));
+        assert!(info.to_string().ends_with(" hello"));
+    }
+
+    #[test]
+    fn test_compose_prefix() {
+        let line = r#"web-1  | {"level":"INFO","msg":"hello"}"#;
+        let info = do_line(
+            &Config {
+                compose_prefix_format: String::from("<{service}>"),
+                ..Config::default()
+            },
+            line,
+        )
+        .unwrap();
+        assert!(info.to_string().contains("<web-1>"));
+        assert!(info.to_string().ends_with(" hello"));
+    }
+
+    #[test]
+    fn test_tkn_prefix() {
+        let config = Config {
+            tkn_prefix_format: String::from("{task}|{step}"),
+            ..Config::default()
+        };
+        let line = r#"[fetch-repository : clone] {"level":"info","msg":"hello"}"#;
+        let info = do_line(&config, line).unwrap();
+        assert!(info.to_string().contains("fetch-repository|clone"));
+        assert!(info.to_string().ends_with(" hello"));
+
+        let config = Config {
+            filter_tasks: vec![String::from("build")],
+            ..Config::default()
+        };
+        assert!(do_line(&config, line).is_none());
+        let config = Config {
+            filter_steps: vec![String::from("clone")],
+            ..Config::default()
+        };
+        assert!(do_line(&config, line).is_some());
+    }
+
+    #[test]
+    fn test_add_timestamp() {
+        let config = Config {
+            add_timestamp: true,
+            time_format: String::from("%Y"),
+            ..Config::default()
+        };
+        let year = chrono::Utc::now().format("%Y").to_string();
+        let now = Paint::fixed(13, &year).dimmed().italic();
+        let info = do_line(&config, "hello").unwrap();
+        assert_eq!(info.to_string(), format!("{now} hello"));
+        let info = do_line(&config, r#"{"level":"info","msg":"hello"}"#).unwrap();
+        assert!(info.to_string().contains(&now.to_string()));
+        // the time of the record is kept
+        let info = do_line(
+            &config,
+            r#"{"level":"info","ts":"2022-04-25T14:20:32Z","msg":"hello"}"#,
+        )
+        .unwrap();
+        assert!(info
+            .to_string()
+            .contains(&Paint::fixed(13, "2022").to_string()));
+    }
+
+    #[test]
+    fn test_add_timestamp_keeps_the_times_of_the_logs() {
+        let config = Config {
+            add_timestamp: true,
+            time_mode: crate::config::TimeMode::Delta,
+            gap: Some(chrono::Duration::minutes(1)),
+            ..Config::default()
+        };
+        let record = r#"{"level":"info","ts":"2022-04-25T14:20:32Z","msg":"hello"}"#;
+        do_line(&config, record).unwrap();
+        let info = do_line(&config, "a raw line").unwrap();
+        assert!(!info.to_string().contains("────"));
+        let record = r#"{"level":"info","ts":"2022-04-25T14:20:33Z","msg":"hello"}"#;
+        let info = do_line(&config, record).unwrap();
+        assert!(!info.to_string().contains("────"));
+        assert!(info.to_string().contains("+00:01.000"));
+    }
+
+    #[test]
+    fn test_bunyan_pino_levels() {
+        let config = Config::default();
+        for (level, expected) in [
+            (10, "DEBUG"),
+            (20, "DEBUG"),
+            (30, "INFO"),
+            (40, "WARNING"),
+            (50, "ERROR"),
+            (60, "FATAL"),
+        ] {
+            let line =
+                format!(r#"{{"level":{level},"time":1650602040628,"msg":"hello","pid":42}}"#);
+            let msg = extract_info(&line, &config);
+            assert_eq!(msg["level"], expected);
+            assert_eq!(msg["msg"], "hello");
+            assert_eq!(msg["ts"], "2022-04-22T04:34:00.628Z");
+        }
+    }
+
+    #[test]
+    fn test_go_slog_logrus() {
+        let config = Config::default();
+        let line = r#"{"time":"2024-01-02T03:04:05.123456+02:00","level":"INFO+2","msg":"hello","user":"bob"}"#;
+        let msg = extract_info(line, &config);
+        assert_eq!(msg["level"], "INFO");
+        assert_eq!(msg["msg"], "hello");
+        assert_eq!(msg["ts"], "2024-01-02T01:04:05.123456Z");
+
+        let line = r#"{"fields.level":"debug","fields.msg":"clash","level":"warning","msg":"hello","time":"2024-01-02T03:04:05-07:00"}"#;
+        let msg = extract_info(line, &config);
+        assert_eq!(msg["level"], "WARNING");
+        assert_eq!(msg["msg"], "hello");
+        assert_eq!(msg["ts"], "2024-01-02T10:04:05Z");
+    }
+
+    #[test]
+    fn test_kail_prefix_with_json_keys() {
+        let line = r#"ns/pod[container]: {"foo": "hello", "bar": "info"}"#;
+        let mut keys = HashMap::new();
+        keys.insert(String::from("msg"), String::from("/foo"));
+        keys.insert(String::from("level"), String::from("/bar"));
+        let msg = extract_info(
+            line,
+            &Config {
+                json_keys: keys,
+                ..Config::default()
+            },
+        );
+        assert_eq!(msg["msg"].matches("ns/pod[container]").count(), 1);
+    }
+
+    #[test]
+    fn test_json_documents() {
+        let config = Config::default();
+        // the documents of the stdout and the stderr are interleaved
+        assert!(do_prefixed_line(&config, "out", "[").is_none());
+        assert!(do_prefixed_line(&config, "err", "{").is_none());
+        assert!(
+            do_prefixed_line(&config, "out", r#"  {"level": "info", "msg": "a ] }"}"#).is_none()
+        );
+        assert!(do_prefixed_line(
+            &config,
+            "err",
+            r#"  "events": [{"timestamp": 1696551429669, "message": "b"}]"#
+        )
+        .is_none());
+        let out = do_prefixed_line(&config, "out", "]").unwrap().to_string();
+        assert!(out.ends_with("a ] }"));
+        let err = do_prefixed_line(&config, "err", "}").unwrap().to_string();
+        assert!(err.ends_with(" b"));
+        assert!(config.state.lock().unwrap().documents.is_empty());
+
+        // an empty document isn't swallowed
+        assert_eq!(do_line(&config, "[]").unwrap().to_string(), "[]");
+        assert!(do_line(&config, "[").is_none());
+        assert_eq!(do_line(&config, "]").unwrap().to_string(), "[]");
+    }
+
     #[test]
     fn test_read_from_file() {
         let mut file: tempfile::NamedTempFile = tempfile::NamedTempFile::new().unwrap();
@@ -197,7 +447,6 @@ mod tests {
 
         let config = Config {
             files: Some(vec![file_path.to_str().unwrap().to_string()]),
-            colouring: false,
             ..Config::default()
         };
         let writeto = &mut Vec::new();
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::config::{self, Config, LogLevel};
+
+// the number of error messages shown in the report
+const TOP_ERRORS: usize = 10;
+// the error messages are cut to this length in the report
+const MESSAGE_LENGTH: usize = 100;
+
+/// The counters of --summary
+#[derive(Debug, Default)]
+pub struct Counters {
+    pub levels: HashMap<String, usize>,
+    /// by namespace/pod of the kail lines
+    pub pods: HashMap<String, usize>,
+    /// the records hidden by --skip-line-regexp or --filter-levels
+    pub skipped: usize,
+    /// the lines we could not parse
+    pub raw: usize,
+    /// the messages of the error and fatal records
+    pub errors: HashMap<String, usize>,
+    /// the earliest and the latest time of the records, they may be unordered
+    pub first: Option<DateTime<FixedOffset>>,
+    pub last: Option<DateTime<FixedOffset>>,
+}
+
+impl Counters {
+    fn count_ts(&mut self, ts: Option<&str>) {
+        let Some(ts) = ts.and_then(|ts| DateTime::parse_from_rfc3339(ts).ok()) else {
+            return;
+        };
+        self.first = Some(self.first.map_or(ts, |first| first.min(ts)));
+        self.last = Some(self.last.map_or(ts, |last| last.max(ts)));
+    }
+}
+
+// the namespace/pod of a kail line
+fn pod(line: &str) -> Option<String> {
+    let captures = crate::parse::kail_regex().captures(line)?;
+    Some(format!("{}/{}", &captures["namespace"], &captures["pod"]))
+}
+
+/// Count a parsed record we show, the fields are the level and the message
+/// without prefix, the ts is an RFC3339 timestamp.
+pub fn count_record(
+    config: &Config,
+    line: &str,
+    fields: &HashMap<String, String>,
+    ts: Option<&str>,
+) {
+    if !config.summary {
+        return;
+    }
+    let level = config::level_from_str(&fields["level"].to_lowercase());
+    let mut state = config.state.lock().unwrap();
+    let counters = &mut state.summary;
+    *counters.levels.entry(level.to_string()).or_default() += 1;
+    counters.count_ts(ts);
+    if let Some(pod) = pod(line) {
+        *counters.pods.entry(pod).or_default() += 1;
+    }
+    if matches!(level, LogLevel::Error | LogLevel::Fatal) {
+        let msg = fields.get("msg").map(|msg| msg.trim()).unwrap_or_default();
+        *counters.errors.entry(msg.to_string()).or_default() += 1;
+    }
+}
+
+/// Count a line we could not parse, the ts is the one of its container if any
+pub fn count_raw(config: &Config, line: &str, ts: Option<&str>) {
+    if !config.summary {
+        return;
+    }
+    let mut state = config.state.lock().unwrap();
+    state.summary.raw += 1;
+    state.summary.count_ts(ts);
+    if let Some(pod) = pod(line) {
+        *state.summary.pods.entry(pod).or_default() += 1;
+    }
+}
+
+/// Count a record hidden by the filters
+pub fn count_skipped(config: &Config) {
+    if config.summary {
+        config.state.lock().unwrap().summary.skipped += 1;
+    }
+}
+
+// the most frequent first, then by name
+fn sorted(counts: &HashMap<String, usize>) -> Vec<(&String, &usize)> {
+    let mut counts: Vec<_> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
+/// The report of --summary
+pub fn report(config: &Config) -> String {
+    let state = config.state.lock().unwrap();
+    let counters = &state.summary;
+    let records: usize = counters.levels.values().sum();
+    let mut report = String::from("snazy: summary\n");
+    let _ = writeln!(
+        report,
+        "  records: {records}, raw lines: {}, skipped: {}",
+        counters.raw, counters.skipped
+    );
+    if let (Some(first), Some(last)) = (counters.first, counters.last) {
+        let show = |ts: DateTime<FixedOffset>| {
+            let ts = ts
+                .with_timezone(&Utc)
+                .format(crate::utils::TS_FORMAT)
+                .to_string();
+            crate::utils::display_ts(&ts, &config.time_format, config.timezone)
+        };
+        let _ = writeln!(
+            report,
+            "  time span: {} - {} ({})",
+            show(first),
+            show(last),
+            crate::utils::format_duration(last - first).trim_start_matches('+')
+        );
+    }
+    if !counters.levels.is_empty() {
+        let levels: Vec<String> = sorted(&counters.levels)
+            .into_iter()
+            .map(|(level, count)| format!("{level} {count}"))
+            .collect();
+        let _ = writeln!(report, "  levels: {}", levels.join(", "));
+    }
+    if !counters.pods.is_empty() {
+        let _ = writeln!(report, "  pods:");
+        for (pod, count) in sorted(&counters.pods) {
+            let _ = writeln!(report, "    {count:>6} {pod}");
+        }
+    }
+    if !counters.errors.is_empty() {
+        let _ = writeln!(report, "  top errors:");
+        for (msg, count) in sorted(&counters.errors).into_iter().take(TOP_ERRORS) {
+            let msg: String = msg.chars().take(MESSAGE_LENGTH).collect();
+            let _ = writeln!(report, "    {count:>6} {msg}");
+        }
+    }
+    report.trim_end().to_string()
+}
+
+/// Show the report on stderr with --summary
+pub fn show(config: &Config) {
+    if config.summary {
+        eprintln!("{}", report(config));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(level: &str, msg: &str) -> HashMap<String, String> {
+        HashMap::from([
+            ("level".to_string(), level.to_string()),
+            ("msg".to_string(), msg.to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_report() {
+        let config = Config {
+            summary: true,
+            ..Config::default()
+        };
+        let line = r#"ns/pod-1[main]: {"level":"error","msg":"boom"}"#;
+        count_record(
+            &config,
+            line,
+            &fields("error", "boom"),
+            Some("2024-01-02T03:04:10Z"),
+        );
+        count_record(
+            &config,
+            line,
+            &fields("ERROR", "boom"),
+            Some("2024-01-02T03:04:05Z"),
+        );
+        count_record(&config, "{}", &fields("fatal", "crashed"), None);
+        count_record(&config, "{}", &fields("info", "hello"), None);
+        count_raw(
+            &config,
+            "ns/pod-2[main]: not json",
+            Some("2024-01-02T03:04:07Z"),
+        );
+        count_skipped(&config);
+        assert_eq!(
+            report(&config),
+            "snazy: summary
+  records: 4, raw lines: 1, skipped: 1
+  time span: 03:04:05 - 03:04:10 (00:05.000)
+  levels: ERROR 2, FATAL 1, INFO 1
+  pods:
+         2 ns/pod-1
+         1 ns/pod-2
+  top errors:
+         2 boom
+         1 crashed"
+        );
+    }
+
+    #[test]
+    fn test_no_summary() {
+        let config = Config::default();
+        count_record(&config, "{}", &fields("error", "boom"), None);
+        count_raw(&config, "raw", None);
+        count_skipped(&config);
+        assert_eq!(
+            report(&config),
+            "snazy: summary\n  records: 0, raw lines: 0, skipped: 0"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+use yansi::Paint;
+
+type Fields = BTreeMap<String, Value>;
+
+// the output of tracing_subscriber::fmt().json()
+#[derive(Deserialize, Debug)]
+struct Tracing {
+    timestamp: String,
+    level: String,
+    fields: Fields,
+    target: Option<String>,
+    span: Option<Fields>,
+    spans: Option<Vec<Fields>>,
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// format the fields as `key=value`, the keys are dimmed
+pub fn key_values<'a>(fields: impl Iterator<Item = (&'a String, &'a Value)>) -> String {
+    fields
+        .map(|(key, value)| format!("{}={}", Paint::new(key).dimmed(), value_to_string(value)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn parse_tracing(line: &str, time_format: &str) -> Option<HashMap<String, String>> {
+    let mut tracing = serde_json::from_str::<Tracing>(line).ok()?;
+    let message = tracing.fields.remove("message")?;
+
+    // the spans from the root to the current one, only the current one when
+    // with_span_list is disabled
+    let spans = tracing
+        .spans
+        .or_else(|| tracing.span.map(|span| vec![span]))
+        .unwrap_or_default();
+    let span_path = spans
+        .iter()
+        .filter_map(|span| span.get("name").map(value_to_string))
+        .collect::<Vec<String>>()
+        .join(":");
+
+    // the target and the spans are shown as a prefix of the message
+    let mut text = Vec::new();
+    if let Some(target) = &tracing.target {
+        text.push(Paint::blue(target).to_string());
+    }
+    if !span_path.is_empty() {
+        text.push(Paint::magenta(span_path).to_string());
+    }
+    text.push(value_to_string(&message).trim().to_string());
+    let fields = tracing
+        .fields
+        .iter()
+        .chain(spans.iter().flatten().filter(|(key, _)| *key != "name"));
+    let fields = key_values(fields);
+    if !fields.is_empty() {
+        text.push(fields);
+    }
+
+    let level = match tracing.level.to_lowercase().as_str() {
+        "warn" => "WARNING".to_string(),
+        "trace" => "DEBUG".to_string(),
+        _ => tracing.level.to_uppercase(),
+    };
+
+    let mut msg = HashMap::new();
+    msg.insert("msg".to_string(), text.join(" "));
+    msg.insert("level".to_string(), level);
+    msg.insert(
+        "ts".to_string(),
+        crate::utils::convert_str_to_ts(&tracing.timestamp, time_format),
+    );
+    Some(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tracing() {
+        let line = r#"{"timestamp":"2024-01-02T03:04:05.123456Z","level":"WARN","fields":{"message":"slow request","elapsed":42},"target":"app::server","span":{"id":7,"name":"request"},"spans":[{"name":"conn","peer":"127.0.0.1"},{"id":7,"name":"request"}]}"#;
+        let msg = parse_tracing(line, "%H:%M:%S").unwrap();
+        assert_eq!(msg["level"], "WARNING");
+        assert_eq!(msg["ts"], "03:04:05");
+        assert_eq!(
+            msg["msg"],
+            format!(
+                "{} {} slow request {}=42 {}=127.0.0.1 {}=7",
+                Paint::blue("app::server"),
+                Paint::magenta("conn:request"),
+                Paint::new("elapsed").dimmed(),
+                Paint::new("peer").dimmed(),
+                Paint::new("id").dimmed()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_tracing_without_spans() {
+        let line = r#"{"timestamp":"2024-01-02T03:04:05.123456Z","level":"INFO","fields":{"message":"hello"}}"#;
+        let msg = parse_tracing(line, "%H:%M:%S").unwrap();
+        assert_eq!(msg["msg"], "hello");
+        assert_eq!(msg["level"], "INFO");
+
+        let line = r#"{"timestamp":"2024-01-02T03:04:05.123456Z","level":"TRACE","fields":{"message":"polling"}}"#;
+        assert_eq!(parse_tracing(line, "%H:%M:%S").unwrap()["level"], "DEBUG");
+
+        assert!(parse_tracing(r#"{"level":"info","msg":"hello"}"#, "%H:%M:%S").is_none());
+    }
+}
@@ -1,6 +1,9 @@
-use chrono::NaiveDateTime;
-use serde_json::Value;
-use yansi::Paint;
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime};
+use chrono_tz::Tz;
+use serde_json::{Number, Value};
+use yansi::{Color, Paint};
 
 /// replace info level DEBUG, WARNING, ERROR, INFO, FATAL by pretty characters
 pub fn level_symbols(level: &str) -> String {
@@ -24,6 +27,32 @@ pub fn color_by_level(level: &str) -> String {
     }
 }
 
+/// choose a colour for a name (e.g: a service), the same name always get the
+/// same colour.
+pub fn colour_for(name: &str) -> Color {
+    const COLOURS: [u8; 12] = [33, 39, 45, 69, 99, 105, 141, 171, 177, 207, 214, 220];
+    // fnv-1a, we want something stable between runs
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    Color::Fixed(COLOURS[(hash % COLOURS.len() as u64) as usize])
+}
+
+/// show a multi-lines text (e.g: a stack trace) as an indented block below a
+/// message
+pub fn indented_block(text: &str) -> String {
+    let mut block = String::new();
+    for line in text.lines() {
+        let _ = write!(block, "\n    {}", Paint::red(line).dimmed());
+    }
+    block
+}
+
+/// the prefix marking the lines coming from stderr
+pub fn stderr_prefix() -> String {
+    Paint::red("stderr").bold().to_string()
+}
+
 pub fn convert_pac_provider_to_fa_icon(provider: &str) -> &str {
     match provider {
         "github" => "",
@@ -35,24 +64,204 @@ pub fn convert_pac_provider_to_fa_icon(provider: &str) -> &str {
     }
 }
 
+/// the format of the timestamps given by the parsers, a RFC3339 date in UTC
+/// which is shown with `display_ts`.
+pub const TS_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.fZ";
+
+/// show a timestamp given by a parser in a timezone, the timestamps we
+/// couldn't parse are shown as is.
+pub fn display_ts(ts: &str, time_format: &str, timezone: Tz) -> String {
+    match DateTime::parse_from_rfc3339(ts) {
+        Ok(ts) => ts.with_timezone(&timezone).format(time_format).to_string(),
+        Err(_) => ts.to_string(),
+    }
+}
+
+/// show a duration between two records, e.g: `+00:01.234` or `+1:02:03.456`
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let sign = if duration < chrono::Duration::zero() {
+        '-'
+    } else {
+        '+'
+    };
+    let millis = duration.num_milliseconds().abs();
+    let (hours, minutes) = (millis / 3_600_000, millis / 60_000 % 60);
+    let (seconds, millis) = (millis / 1000 % 60, millis % 1000);
+    if hours > 0 {
+        format!("{sign}{hours}:{minutes:02}:{seconds:02}.{millis:03}")
+    } else {
+        format!("{sign}{minutes:02}:{seconds:02}.{millis:03}")
+    }
+}
+
+/// parse a duration like `30s`, `5m`, `1h` or `500ms`
+pub fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    let unit = s.trim_start_matches(|c: char| c.is_ascii_digit());
+    let value = s[..s.len() - unit.len()]
+        .parse::<i64>()
+        .map_err(|_| format!("invalid duration {s}, e.g: 30s"))?;
+    let milliseconds = match unit {
+        "ms" => 1,
+        "s" | "" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        _ => {
+            return Err(format!(
+                "invalid unit {unit} in {s}, it can be ms, s, m or h"
+            ))
+        }
+    };
+    value
+        .checked_mul(milliseconds)
+        .map(chrono::Duration::milliseconds)
+        .ok_or_else(|| format!("duration {s} is too long"))
+}
+
+/// get the timezone from its name, `local` is the timezone of the system.
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    if name.eq_ignore_ascii_case("local") {
+        return Ok(local_timezone(
+            iana_time_zone::get_timezone().map_err(|e| e.to_string()),
+        ));
+    }
+    name.parse::<Tz>()
+}
+
+// the timezone of the system or UTC when we can't find it, e.g: in a container
+// without /etc/localtime
+fn local_timezone(name: Result<String, String>) -> Tz {
+    match name.and_then(|name| name.parse::<Tz>()) {
+        Ok(timezone) => timezone,
+        Err(e) => {
+            eprintln!("cannot find the local timezone, using UTC: {e}");
+            Tz::UTC
+        }
+    }
+}
+
 pub fn convert_str_to_ts(s: &str, time_format: &str) -> String {
-    // try to convert s to a nativdatetime if fail then return just the string
-    if let Ok(ts) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S.%fZ") {
-        return ts.format(time_format).to_string();
+    // try to convert s to a datetime if fail then return just the string, the
+    // RFC3339 dates can have any offset and precision and are converted to UTC,
+    // the ones without an offset are assumed to be in UTC.
+    if let Ok(ts) = DateTime::parse_from_rfc3339(s) {
+        return ts.naive_utc().and_utc().format(time_format).to_string();
+    }
+    if let Ok(ts) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+        return ts.and_utc().format(time_format).to_string();
     }
 
     s.to_string()
 }
 
-fn convert_unix_ts(value: i64, time_format: &str) -> String {
-    let ts = NaiveDateTime::from_timestamp_opt(value, 0).unwrap();
+/// convert a syslog RFC3164 timestamp (e.g: `Oct 11 22:14:15`) which doesn't have
+/// a year, we assume it's from the current year.
+pub fn convert_bsd_ts(s: &str, time_format: &str) -> String {
+    let year = Local::now().year();
+    if let Ok(ts) = NaiveDateTime::parse_from_str(&format!("{year} {s}"), "%Y %b %e %H:%M:%S") {
+        return ts.and_utc().format(time_format).to_string();
+    }
+
+    s.to_string()
+}
+
+/// convert the time of the apache and nginx access logs (e.g:
+/// `10/Oct/2000:13:55:36 -0700`), shown in UTC like the RFC3339 dates.
+pub fn convert_clf_ts(s: &str, time_format: &str) -> String {
+    if let Ok(ts) = DateTime::parse_from_str(s, "%d/%b/%Y:%H:%M:%S %z") {
+        return ts.naive_utc().and_utc().format(time_format).to_string();
+    }
+
+    convert_str_to_ts(s, time_format)
+}
+
+fn convert_unix_ts(nanoseconds: i128, time_format: &str) -> String {
+    let seconds = i64::try_from(nanoseconds.div_euclid(1_000_000_000)).unwrap_or_default();
+    let nanoseconds = u32::try_from(nanoseconds.rem_euclid(1_000_000_000)).unwrap_or_default();
+    let ts = DateTime::from_timestamp(seconds, nanoseconds).unwrap_or_default();
     ts.format(time_format).to_string()
 }
 
+/// guess if an epoch is in seconds, milliseconds, microseconds or nanoseconds
+/// by its magnitude and return it in seconds.
+pub fn epoch_in_seconds(value: f64) -> f64 {
+    match value.abs() {
+        v if v < 1e11 => value,
+        v if v < 1e14 => value / 1e3,
+        v if v < 1e17 => value / 1e6,
+        _ => value / 1e9,
+    }
+}
+
+// the nanoseconds in a unit of epoch guessed by its magnitude
+fn epoch_scale(value: i128) -> i128 {
+    match value.abs() {
+        v if v < 100_000_000_000 => 1_000_000_000,
+        v if v < 100_000_000_000_000 => 1_000_000,
+        v if v < 100_000_000_000_000_000 => 1_000,
+        _ => 1,
+    }
+}
+
+// the nanoseconds of a decimal epoch, e.g: `1650602040.6289625`, taken from its
+// digits as a float would not keep all of them
+fn decimal_in_nanoseconds(text: &str) -> Option<i128> {
+    let (whole, fraction) = text.split_once('.')?;
+    if fraction.is_empty() || !fraction.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let whole_value = whole.parse::<i128>().ok()?;
+    let scale = epoch_scale(whole_value);
+    let digits: String = fraction
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(scale.ilog10() as usize)
+        .collect();
+    let nanoseconds = whole_value.abs() * scale + digits.parse::<i128>().unwrap_or(0);
+    Some(if whole.starts_with('-') {
+        -nanoseconds
+    } else {
+        nanoseconds
+    })
+}
+
+/// the nanoseconds of an epoch in any unit, the integers and the decimals are
+/// kept as is to not lose any precision.
+fn epoch_in_nanoseconds(value: &Number) -> Option<i128> {
+    if let Some(value) = value.as_i64().map(i128::from) {
+        return Some(value * epoch_scale(value));
+    }
+    if let Some(value) = value.as_u64() {
+        return Some(i128::from(value));
+    }
+    if let Some(nanoseconds) = decimal_in_nanoseconds(&value.to_string()) {
+        return Some(nanoseconds);
+    }
+    let seconds = epoch_in_seconds(value.as_f64()?);
+    let whole = seconds.floor();
+    Some(whole as i128 * 1_000_000_000 + ((seconds - whole) * 1e9).round() as i128)
+}
+
+// an epoch as a string has at least 9 digits, e.g: "1650602040.628" but not a
+// date like "20240101"
+fn is_epoch_str(s: &str) -> bool {
+    let whole = s.split_once('.').map_or(s, |(whole, _)| whole);
+    whole.len() >= 9 && whole.bytes().all(|c| c.is_ascii_digit())
+}
+
 pub fn convert_ts_float_or_str(value: &Value, time_format: &str) -> String {
     match value {
+        Value::String(s) if is_epoch_str(s) => match decimal_in_nanoseconds(s) {
+            Some(nanoseconds) => convert_unix_ts(nanoseconds, time_format),
+            None => match s.parse::<Number>() {
+                Ok(n) => convert_ts_float_or_str(&Value::Number(n), time_format),
+                Err(_) => convert_str_to_ts(s.as_str(), time_format),
+            },
+        },
         Value::String(s) => convert_str_to_ts(s.as_str(), time_format),
-        Value::Number(n) => convert_unix_ts(n.as_f64().unwrap() as i64, time_format),
+        Value::Number(n) => match epoch_in_nanoseconds(n) {
+            Some(nanoseconds) => convert_unix_ts(nanoseconds, time_format),
+            None => n.to_string(),
+        },
         _ => String::new(),
     }
 }
@@ -93,4 +302,154 @@ mod tests {
             "2020-01-01 00:00:00"
         );
     }
+
+    #[test]
+    fn test_convert_rfc3339_offsets() {
+        for ts in [
+            "2024-01-02T03:04:05.123+02:00",
+            "2024-01-02T03:04:05+02:00",
+            "2024-01-02T01:04:05.123456789Z",
+            "2024-01-02T01:04:05Z",
+            "2024-01-01T20:04:05.1-05:00",
+            "2024-01-02T01:04:05.123",
+        ] {
+            assert_eq!(
+                convert_str_to_ts(ts, "%Y-%m-%d %H:%M:%S"),
+                "2024-01-02 01:04:05",
+                "{ts}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_epochs() {
+        for epoch in [
+            Value::from(1_650_602_040),
+            Value::from(1_650_602_040.628_962_5),
+            Value::from(1_650_602_040_628_u64),
+            Value::from(1_650_602_040_628_962_u64),
+            Value::from(1_650_602_040_628_962_500_u64),
+        ] {
+            assert_eq!(
+                convert_ts_float_or_str(&epoch, "%Y-%m-%d %H:%M:%S"),
+                "2022-04-22 04:34:00"
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_epochs_precision() {
+        for (epoch, expected) in [
+            (Value::from(1_650_602_040.628_962_5), "04:34:00.628"),
+            (Value::from(1_650_602_040_628_u64), "04:34:00.628000000"),
+            (Value::from(1_650_602_040_628_962_u64), "04:34:00.628962000"),
+            (
+                Value::from(1_650_602_040_628_962_500_u64),
+                "04:34:00.628962500",
+            ),
+            (Value::from("1650602040.628"), "04:34:00.628"),
+            (Value::from(1_650_602_040.628_962_5), "04:34:00.628962500"),
+            (Value::from("1650602040.628962517"), "04:34:00.628962517"),
+            (Value::from("1650602040628.9625"), "04:34:00.628962500"),
+        ] {
+            let format = if expected.len() > 12 {
+                "%H:%M:%S%.9f"
+            } else {
+                "%H:%M:%S%.3f"
+            };
+            assert_eq!(convert_ts_float_or_str(&epoch, format), expected);
+        }
+        for not_epoch in ["20240101", "42", "-1650602040", "1650602040.x"] {
+            assert_eq!(
+                convert_ts_float_or_str(&Value::from(not_epoch), TS_FORMAT),
+                not_epoch
+            );
+        }
+        assert_eq!(
+            convert_str_to_ts("2022-04-22T04:34:00.628962500Z", TS_FORMAT),
+            "2022-04-22T04:34:00.628962500Z"
+        );
+    }
+
+    #[test]
+    fn test_colour_for() {
+        assert_eq!(colour_for("web-1"), colour_for("web-1"));
+        assert_ne!(colour_for("web-1"), colour_for("db-1"));
+    }
+
+    #[test]
+    fn test_convert_clf_ts() {
+        assert_eq!(
+            convert_clf_ts("10/Oct/2000:13:55:36 -0700", "%Y-%m-%d %H:%M:%S"),
+            "2000-10-10 20:55:36"
+        );
+        assert_eq!(
+            convert_clf_ts("2024-01-02T03:04:05+00:00", "%H:%M:%S"),
+            "03:04:05"
+        );
+    }
+
+    #[test]
+    fn test_display_ts() {
+        let ts = convert_str_to_ts("2024-01-02T03:04:05.123+02:00", TS_FORMAT);
+        assert_eq!(ts, "2024-01-02T01:04:05.123Z");
+        assert_eq!(display_ts(&ts, "%H:%M:%S %Z", Tz::UTC), "01:04:05 UTC");
+        assert_eq!(
+            display_ts(
+                &ts,
+                "%H:%M:%S %z %Z",
+                parse_timezone("Europe/Paris").unwrap()
+            ),
+            "02:04:05 +0100 CET"
+        );
+        assert_eq!(display_ts("yesterday", "%H:%M:%S", Tz::UTC), "yesterday");
+        assert!(parse_timezone("local").is_ok());
+        assert!(parse_timezone("Mars/Olympus").is_err());
+        assert_eq!(
+            local_timezone(Ok(String::from("Europe/Paris"))),
+            Tz::Europe__Paris
+        );
+        assert_eq!(local_timezone(Err(String::from("no localtime"))), Tz::UTC);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(
+            format_duration(chrono::Duration::milliseconds(1234)),
+            "+00:01.234"
+        );
+        assert_eq!(
+            format_duration(chrono::Duration::milliseconds(3_723_456)),
+            "+1:02:03.456"
+        );
+        assert_eq!(
+            format_duration(chrono::Duration::milliseconds(-500)),
+            "-00:00.500"
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Ok(chrono::Duration::seconds(30)));
+        assert_eq!(parse_duration("5m"), Ok(chrono::Duration::minutes(5)));
+        assert_eq!(parse_duration("1h"), Ok(chrono::Duration::hours(1)));
+        assert_eq!(
+            parse_duration("500ms"),
+            Ok(chrono::Duration::milliseconds(500))
+        );
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("3d").is_err());
+        assert!(parse_duration("9999999999999999s").is_err());
+        assert!(parse_duration("99999999999999h").is_err());
+    }
+
+    #[test]
+    fn test_convert_bsd_ts() {
+        assert_eq!(
+            convert_bsd_ts("Oct  1 22:14:15", "%m-%d %H:%M:%S"),
+            "10-01 22:14:15"
+        );
+        assert_eq!(convert_bsd_ts("Oct 11 22:14:15", "%H:%M:%S"), "22:14:15");
+        assert_eq!(convert_bsd_ts("not a date", "%H:%M:%S"), "not a date");
+    }
 }
@@ -0,0 +1,142 @@
+use regex::Regex;
+
+use crate::config::{self, Config, LogLevel};
+
+/// A condition of --fail-on or --stop-on, a level (and the ones above) or a
+/// regexp on the line
+#[derive(Debug, Clone)]
+pub enum Condition {
+    Level(LogLevel),
+    Regexp(Regex),
+}
+
+pub fn parse_condition(condition: &str) -> Result<Condition, String> {
+    let level = match condition.to_lowercase().as_str() {
+        "debug" => LogLevel::Debug,
+        "info" => LogLevel::Info,
+        "warn" | "warning" => LogLevel::Warning,
+        "err" | "error" => LogLevel::Error,
+        "fatal" => LogLevel::Fatal,
+        _ => {
+            return Regex::new(condition)
+                .map(Condition::Regexp)
+                .map_err(|e| e.to_string())
+        }
+    };
+    Ok(Condition::Level(level))
+}
+
+fn severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Debug => 0,
+        LogLevel::Info => 1,
+        LogLevel::Warning => 2,
+        LogLevel::Error => 3,
+        LogLevel::Fatal => 4,
+    }
+}
+
+impl Condition {
+    fn matches(&self, line: &str, level: Option<&str>) -> bool {
+        match self {
+            Condition::Level(wanted) => level.is_some_and(|level| {
+                severity(*config::level_from_str(&level.to_lowercase())) >= severity(*wanted)
+            }),
+            Condition::Regexp(re) => re.is_match(line),
+        }
+    }
+}
+
+/// Count the records matching --fail-on, --stop-on and --max-count, the level
+/// is the one of the record if we could parse it.
+pub fn check(config: &Config, line: &str, level: Option<&str>) {
+    if config.fail_on.is_empty() && config.stop_on.is_empty() && config.max_count.is_none() {
+        return;
+    }
+    let failed = config.fail_on.iter().any(|c| c.matches(line, level));
+    let stop = config.stop_on.iter().any(|c| c.matches(line, level));
+    let mut state = config.state.lock().unwrap();
+    if failed {
+        state.failures += 1;
+        state.first_failure.get_or_insert_with(|| line.to_string());
+    }
+    // without conditions --max-count counts all the records
+    if failed || stop || (config.fail_on.is_empty() && config.stop_on.is_empty()) {
+        state.matches += 1;
+    }
+    if stop {
+        state.stopped = Some(format!("--stop-on matched {line}"));
+    } else if config.max_count.is_some_and(|max| state.matches >= max) {
+        state.stopped = Some(format!(
+            "--max-count {} reached",
+            config.max_count.unwrap_or_default()
+        ));
+    }
+}
+
+/// If we should stop reading the logs
+pub fn stopped(config: &Config) -> bool {
+    config.state.lock().unwrap().stopped.is_some()
+}
+
+/// Show on stderr why we stopped or failed, return the exit code: 1 when a
+/// record matched --fail-on.
+pub fn summary(config: &Config) -> i32 {
+    let state = config.state.lock().unwrap();
+    if let Some(reason) = &state.stopped {
+        eprintln!("snazy: stopped, {reason}");
+    }
+    let Some(first) = &state.first_failure else {
+        return 0;
+    };
+    eprintln!(
+        "snazy: failing, {} record{} matched --fail-on, the first one: {first}",
+        state.failures,
+        if state.failures > 1 { "s" } else { "" }
+    );
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conditions() {
+        let error = parse_condition("error").unwrap();
+        assert!(error.matches("boom", Some("ERROR")));
+        assert!(error.matches("boom", Some("fatal")));
+        assert!(!error.matches("boom", Some("WARNING")));
+        assert!(!error.matches("boom", None));
+        let regexp = parse_condition(r"FAIL:\s\w+").unwrap();
+        assert!(regexp.matches("--- FAIL: TestFoo", None));
+        assert!(!regexp.matches("--- PASS: TestFoo", Some("ERROR")));
+        assert!(parse_condition("(").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let config = Config {
+            fail_on: vec![parse_condition("error").unwrap()],
+            max_count: Some(2),
+            ..Config::default()
+        };
+        check(&config, "fine", Some("INFO"));
+        check(&config, "boom", Some("ERROR"));
+        assert!(!stopped(&config));
+        check(&config, "bang", Some("ERROR"));
+        assert!(stopped(&config));
+        assert_eq!(summary(&config), 1);
+        assert_eq!(config.state.lock().unwrap().failures, 2);
+
+        let config = Config {
+            stop_on: vec![parse_condition("ready").unwrap()],
+            ..Config::default()
+        };
+        check(&config, "starting", None);
+        assert!(!stopped(&config));
+        check(&config, "server is ready", None);
+        assert!(stopped(&config));
+        assert_eq!(summary(&config), 0);
+    }
+}
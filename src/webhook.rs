@@ -0,0 +1,187 @@
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use yansi::Paint;
+
+// how long we wait for the other records of a batch before posting it
+const BATCH_WAIT: Duration = Duration::from_secs(1);
+// the delay before the first retry, doubled on every other one
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+// the records waiting to be posted, the others are dropped
+const QUEUE_SIZE: usize = 1024;
+
+/// Post the records matching an action as json to an url, e.g: a local alert
+/// relay. The records are sent by a thread, in a json array of up to `batch`
+/// records or as a single object when the batch is 1.
+#[derive(Debug)]
+pub struct Webhook {
+    url: String,
+    batch: usize,
+    timeout: Duration,
+    retries: u32,
+    sender: Mutex<Option<(SyncSender<Value>, JoinHandle<()>)>>,
+}
+
+impl Webhook {
+    pub fn new(url: String, batch: usize, timeout: Duration, retries: u32) -> Webhook {
+        Webhook {
+            url,
+            batch: batch.max(1),
+            timeout,
+            retries,
+            sender: Mutex::default(),
+        }
+    }
+
+    /// Queue a record, the thread posting them is started on the first one. The
+    /// record is dropped when the url can't keep up.
+    pub fn send(&self, name: &str, record: Value) {
+        let mut sender = self.sender.lock().unwrap();
+        let (tx, _) = sender.get_or_insert_with(|| {
+            let (tx, rx) = mpsc::sync_channel::<Value>(QUEUE_SIZE);
+            let agent = ureq::AgentBuilder::new().timeout(self.timeout).build();
+            let (url, batch, retries) = (self.url.clone(), self.batch, self.retries);
+            let name = name.to_string();
+            let handle = thread::spawn(move || {
+                while let Ok(first) = rx.recv() {
+                    let mut records = vec![first];
+                    let deadline = Instant::now() + BATCH_WAIT;
+                    while records.len() < batch {
+                        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                            Ok(record) => records.push(record),
+                            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                                break
+                            }
+                        }
+                    }
+                    let body = if batch == 1 {
+                        records.remove(0)
+                    } else {
+                        Value::Array(records)
+                    };
+                    match post(&agent, &url, &body.to_string(), retries) {
+                        Ok(()) => eprintln!("Posted record: for action: {}", Paint::cyan(&name)),
+                        Err(e) => eprintln!("Action {} failed: {e}", Paint::cyan(&name)),
+                    }
+                }
+            });
+            (tx, handle)
+        });
+        if let Err(TrySendError::Full(_)) = tx.try_send(record) {
+            eprintln!(
+                "Dropped record: for action: {}, {QUEUE_SIZE} records are waiting to be posted",
+                Paint::cyan(name)
+            );
+        }
+    }
+
+    /// Post the records still queued and stop the thread.
+    pub fn flush(&self) {
+        if let Some((tx, handle)) = self.sender.lock().unwrap().take() {
+            drop(tx);
+            let _ = handle.join();
+        }
+    }
+}
+
+// post the body, retrying on the connection errors, the server errors and when
+// we are rate limited
+fn post(agent: &ureq::Agent, url: &str, body: &str, retries: u32) -> Result<(), String> {
+    let mut attempt = 0;
+    loop {
+        let error = match agent
+            .post(url)
+            .set("Content-Type", "application/json")
+            .send_string(body)
+        {
+            Ok(_) => return Ok(()),
+            Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                return Err(format!("{url} returned {code}"))
+            }
+            Err(e) => e.to_string(),
+        };
+        if attempt >= retries {
+            return Err(error);
+        }
+        thread::sleep(RETRY_DELAY * 2u32.pow(attempt));
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    // a local http server answering the statuses in order, it returns the
+    // bodies it received
+    fn serve(statuses: &'static [u16]) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {status} STATUS\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+            bodies
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_post_retries() {
+        let (url, server) = serve(&[500, 429, 200]);
+        let webhook = Webhook::new(url, 1, Duration::from_secs(5), 2);
+        webhook.send("hook", serde_json::json!({"msg": "hello"}));
+        webhook.flush();
+        let bodies = server.join().unwrap();
+        assert_eq!(bodies, vec![r#"{"msg":"hello"}"#; 3]);
+    }
+
+    #[test]
+    fn test_post_batch() {
+        let (url, server) = serve(&[200]);
+        let webhook = Webhook::new(url, 2, Duration::from_secs(5), 0);
+        webhook.send("hook", serde_json::json!({"msg": "one"}));
+        webhook.send("hook", serde_json::json!({"msg": "two"}));
+        webhook.flush();
+        assert_eq!(
+            server.join().unwrap(),
+            vec![r#"[{"msg":"one"},{"msg":"two"}]"#]
+        );
+    }
+
+    #[test]
+    fn test_post_client_error() {
+        let (url, server) = serve(&[404]);
+        let agent = ureq::AgentBuilder::new().build();
+        assert!(post(&agent, &url, "{}", 3).is_err());
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+}
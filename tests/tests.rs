@@ -27,6 +27,38 @@ snazytest!(
     false
 );
 
+snazytest!(
+    timezone_date,
+    ["--timezone", "Europe/Paris", "--time-format", "%H:%M:%S %Z"],
+    r#"{"level":"info", "ts": "2022-04-25T14:20:32.505637358Z", "msg":"foo"}"#,
+    "INFO                16:20:32 CEST foo\n",
+    false
+);
+
+snazytest!(
+    time_mode_delta,
+    ["--time-mode", "delta"],
+    "{\"level\":\"info\",\"ts\":\"2022-04-25T14:20:32.5Z\",\"msg\":\"a\"}\n{\"level\":\"info\",\"ts\":\"2022-04-25T14:20:33.734Z\",\"msg\":\"b\"}",
+    "INFO                +00:00.000 a\nINFO                +00:01.234 b\n",
+    false
+);
+
+snazytest!(
+    gap_separator,
+    ["--gap", "1s"],
+    "{\"level\":\"info\",\"ts\":\"2022-04-25T14:20:32.5Z\",\"msg\":\"a\"}\n{\"level\":\"info\",\"ts\":\"2022-04-25T14:20:33.0Z\",\"msg\":\"b\"}\n{\"level\":\"info\",\"ts\":\"2022-04-25T14:21:03.0Z\",\"msg\":\"c\"}",
+    "INFO                14:20:32 a\nINFO                14:20:33 b\n──── 00:30.000 ────\nINFO                14:21:03 c\n",
+    false
+);
+
+snazytest!(
+    hidden_records_are_not_timed,
+    ["--time-mode", "delta", "--gap", "1m", "--skip-line-regexp", "hidden"],
+    "{\"level\":\"info\",\"ts\":\"2022-04-25T10:00:00Z\",\"msg\":\"a\"}\n{\"level\":\"info\",\"ts\":\"2022-04-25T10:05:00Z\",\"msg\":\"hidden\"}\n{\"level\":\"info\",\"ts\":\"2022-04-25T10:05:01Z\",\"msg\":\"b\"}",
+    "INFO                +00:00.000 a\n──── 05:01.000 ────\nINFO                +05:01.000 b\n",
+    false
+);
+
 snazytest!(
     floated_date,
     [""],
@@ -35,6 +67,95 @@ snazytest!(
     false
 );
 
+snazytest!(
+    floated_date_milliseconds,
+    ["--time-format", "ms"],
+    r#"{"level":"info", "ts": 1650602040.6289625, "msg":"foo"}"#,
+    "INFO                04:34:00.628 foo\n",
+    false
+);
+
+snazytest!(
+    pino_numeric_level,
+    [""],
+    r#"{"level":50,"time":1650602040628,"pid":42,"hostname":"box","msg":"foo"}"#,
+    "ERROR              04:34:00 foo\n",
+    false
+);
+
+snazytest!(
+    rust_tracing_json,
+    [""],
+    r#"{"timestamp":"2024-01-02T03:04:05.123456Z","level":"INFO","fields":{"message":"foo","user":"bob"},"target":"app","spans":[{"name":"req","id":1}]}"#,
+    "INFO                03:04:05 app req foo user=bob id=1\n",
+    false
+);
+
+snazytest!(
+    go_slog,
+    [""],
+    r#"{"time":"2024-01-02T03:04:05.123+02:00","level":"ERROR","msg":"foo","err":"boom"}"#,
+    "ERROR              01:04:05 foo\n",
+    false
+);
+
+snazytest!(
+    opentelemetry_otlp_json,
+    [""],
+    r#"{"resourceLogs":[{"resource":{"attributes":[{"key":"service.name","value":{"stringValue":"checkout"}}]},"scopeLogs":[{"logRecords":[{"timeUnixNano":"1650602040628962500","severityNumber":9,"body":{"stringValue":"foo"}},{"timeUnixNano":"1650602041628962500","severityNumber":17,"body":{"stringValue":"bar"}}]}]}]}"#,
+    "INFO                04:34:00 checkout foo\nERROR              04:34:01 checkout bar\n",
+    false
+);
+
+snazytest!(
+    elastic_common_schema,
+    [""],
+    r#"{"@timestamp":"2024-01-02T03:04:05.123Z","log.level":"info","message":"foo","ecs.version":"1.6.0"}"#,
+    "INFO                03:04:05 foo\n",
+    false
+);
+
+snazytest!(
+    gcloud_logging_read,
+    [""],
+    "[\n  {\n    \"insertId\": \"abc\",\n    \"resource\": {\"type\": \"k8s_container\", \"labels\": {\"namespace_name\": \"ns\", \"pod_name\": \"pod\", \"container_name\": \"ctr\"}},\n    \"severity\": \"ERROR\",\n    \"textPayload\": \"boom\",\n    \"timestamp\": \"2023-10-06T00:17:09.669794202Z\"\n  }\n]",
+    "ERROR              00:17:09 ns/pod[ctr] boom\n",
+    false
+);
+
+snazytest!(
+    cloudwatch_events,
+    [""],
+    r#"{"events":[{"timestamp":1696551429669,"message":"{\"level\":\"info\",\"msg\":\"foo\"}"},{"timestamp":1696551430669,"message":"plain"}]}"#,
+    "INFO                00:17:09 foo\n00:17:10 plain\n",
+    false
+);
+
+snazytest!(
+    unfinished_json_document,
+    [""],
+    "{\n  \"a\": 1",
+    "{\n  \"a\": 1\n",
+    false
+);
+
+snazytest!(
+    nginx_access_log,
+    [""],
+    r#"10.0.0.1 - - [02/Jan/2024:03:04:05 +0000] "GET /api HTTP/1.1" 404 12 "-" "curl/8.0" 80 0.002 [default-api-80] [] 10.1.0.4:8080 12 0.002 404 4f8c"#,
+    "WARN                03:04:05 10.0.0.1 GET /api 404 12B 0.002s \"curl/8.0\"\n",
+    false
+);
+
+snazytest!(
+    app_log_with_status_and_method,
+    [""],
+    r#"{"level":"info","msg":"request handled","status":200,"method":"GET","path":"/healthz"}
+{"level":"error","ts":"2024-01-02T03:04:05Z","msg":"upstream failed","status":502,"method":"POST","path":"/api"}"#,
+    "INFO                 request handled\nERROR              03:04:05 upstream failed\n",
+    false
+);
+
 snazytest!(raw_non_json, [""], "Hello Moto", "Hello Moto\n", false);
 
 snazytest!(
@@ -85,6 +206,33 @@ snazytest!(
     false
 );
 
+snazytest!(
+    compose_prefix,
+    [""],
+    r#"web-1     | {"level":"INFO","msg":"Hello Moto"}
+db_1  | raw line"#,
+    "web-1 Hello Moto",
+    true
+);
+
+snazytest!(
+    tkn_filter_tasks,
+    ["--filter-tasks", "build"],
+    r#"[fetch-repository : clone] cloning
+[build : compile] {"level":"error","msg":"boom"}"#,
+    "ERROR               [build:compile] boom\n",
+    false
+);
+
+snazytest!(
+    serilog_clef,
+    [""],
+    r#"{"@t":"2016-06-07T03:44:57.8532799Z","@mt":"User {UserId} logged in","UserId":42}
+{"@t":"2016-06-07T03:44:58.8532799Z","@m":"Oops","@l":"Warning"}"#,
+    "INFO                03:44:57 User 42 logged in\nWARN                03:44:58 Oops\n",
+    false
+);
+
 snazytest!(
     pac_output_github,
     [""],
@@ -173,12 +321,160 @@ snazytest!(
     false
 );
 
+snazytest!(
+    docker_json_file,
+    [""],
+    r#"{"log":"{\"level\":\"info\",\"msg\":\"foo\"}\n","stream":"stdout","time":"2023-10-06T00:17:09.669794202Z"}"#,
+    "INFO                00:17:09 foo\n",
+    false
+);
+
+snazytest!(
+    docker_json_file_stderr,
+    [""],
+    r#"{"log":"raw line\n","stream":"stderr","time":"2023-10-06T00:17:10.669794202Z"}"#,
+    "00:17:10 stderr raw line\n",
+    false
+);
+
+snazytest!(
+    cri_partial_lines,
+    [""],
+    r#"2023-10-06T00:17:09.669794202Z stdout P {"level":"error",
+2023-10-06T00:17:09.669794202Z stdout F "ts":"2022-04-25T14:20:32.505637358Z","msg":"foo"}"#,
+    "ERROR              14:20:32 foo\n",
+    false
+);
+
+snazytest!(
+    github_actions_log,
+    [""],
+    "build\tRun tests\t2023-11-20T10:01:02.1234567Z ##[group]Run make test
+build\tRun tests\t2023-11-20T10:01:02.1234567Z make test
+build\tRun tests\t2023-11-20T10:01:02.1234567Z ##[endgroup]
+build\tRun tests\t2023-11-20T10:01:03.1234567Z {\"level\":\"info\",\"msg\":\"foo\"}
+build\tRun tests\t2023-11-20T10:01:04.1234567Z ##[error]Process completed with exit code 2.",
+    "━━ build › Run tests\n▼ Run make test\n10:01:02 make test\nINFO                10:01:03 foo\nERROR              10:01:04 Process completed with exit code 2.\n",
+    false
+);
+
+snazytest!(
+    github_actions_collapse_groups,
+    ["--collapse-groups"],
+    "build\tRun tests\t2023-11-20T10:01:02.1234567Z ##[group]Run make test
+build\tRun tests\t2023-11-20T10:01:02.1234567Z make test
+build\tRun tests\t2023-11-20T10:01:02.1234567Z ##[warning]flaky
+build\tRun tests\t2023-11-20T10:01:02.1234567Z ##[endgroup]
+build\tRun tests\t2023-11-20T10:01:03.1234567Z done",
+    "━━ build › Run tests\n▶ Run make test\nWARN                10:01:02 flaky\n10:01:03 done\n",
+    false
+);
+
+snazytest!(
+    run_command,
+    ["--", "cat"],
+    r#"{"level":"info","msg":"foo"}"#,
+    "INFO                 foo\n",
+    false
+);
+
+#[test]
+fn run_command_exit_code() {
+    let tenv = testenv::TestEnv::new();
+    let output = process::Command::new(tenv.snazy_exe)
+        .args(["--", "sh", "-c", "echo hello >&2; exit 3"])
+        .output()
+        .expect("snazy output");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "stderr hello\n");
+}
+
+snazytest!(
+    json_keys_subset,
+    ["-k", "msg=/foo", "-k", "caller=/src"],
+    r#"{"foo":"hello","level":"info","src":"main.go:12"}"#,
+    "INFO                 hello caller=main.go:12\n",
+    false
+);
+
+snazytest!(
+    json_msg_format,
+    [
+        "-k",
+        "msg=/foo",
+        "-k",
+        "logger=/lg",
+        "--json-msg-format",
+        "{logger}: {msg}"
+    ],
+    r#"{"foo":"hello","level":"info","lg":"app"}"#,
+    "INFO                 app: hello\n",
+    false
+);
+
+snazytest!(
+    stop_on_match,
+    ["--stop-on", "ready"],
+    "starting\nserver is ready\nserving",
+    "starting\nserver is ready\n",
+    false
+);
+
+snazytest!(
+    max_count,
+    ["--max-count", "2"],
+    "--- PASS: TestFoo\n--- FAIL: TestBar\n--- FAIL: TestBaz",
+    "--- PASS: TestFoo\n--- FAIL: TestBar\n",
+    false
+);
+
+#[test]
+fn fail_on_exit_code() {
+    let tenv = testenv::TestEnv::new();
+    let output = process::Command::new(tenv.snazy_exe)
+        .args([
+            "--fail-on",
+            "error",
+            "--",
+            "printf",
+            r#"{"level":"info","msg":"fine"}\n{"level":"error","msg":"boom"}\n"#,
+        ])
+        .output()
+        .expect("snazy output");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "INFO                 fine\nERROR               boom\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(r#"1 record matched --fail-on, the first one: {"level":"error","msg":"boom"}"#));
+}
+
+#[test]
+fn summary_report() {
+    let tenv = testenv::TestEnv::new();
+    let output = process::Command::new(tenv.snazy_exe)
+        .args([
+            "--summary",
+            "--",
+            "printf",
+            r#"{"level":"error","msg":"boom"}\n{"level":"error","msg":"boom"}\nnot json\n"#,
+        ])
+        .output()
+        .expect("snazy output");
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("records: 2, raw lines: 1, skipped: 0"));
+    assert!(stderr.contains("levels: ERROR 2"));
+    assert!(stderr.contains("     2 boom"));
+}
+
 #[test]
 #[should_panic]
-fn all_json_keys_need_tobe_specified() {
+fn json_keys_need_a_pointer() {
     let tenv = testenv::TestEnv::new();
     let mut cmd = process::Command::new(tenv.snazy_exe);
-    let args = &["-k", "msg=/foo"];
+    let args = &["-k", "msg"];
     cmd.args(args);
     // Run *snazy*.
     let output = cmd.output().expect("snazy output");